@@ -0,0 +1,260 @@
+use crate::consumable::Consumable;
+use crate::error::AppError;
+use crate::escape::{escape_csv, sanitize_line};
+use crate::price::{Accessory, Price};
+
+use chrono::prelude::Utc;
+use serde::Serialize;
+
+/// One material requirement of a pleat. Profiles are identified by series and colour,
+/// with the code for custom RAL and wood-grain profiles (see
+/// [`Consumable::profile_color`]), corner pieces by series and the mesh by its type;
+/// everything else is the same part for every pleat. Add-ons are identified by their name.
+///
+/// `unit` is `cm` for lengths, `cm2` for areas and `adet` for pieces. Profile lengths
+/// are what the cut list cuts, not the weighted figures the costing uses.
+#[derive(Serialize, Clone)]
+pub struct BomItem {
+    pub item: String,
+    pub series: Option<&'static str>,
+    pub color: Option<String>,
+    pub quantity: f32,
+    pub unit: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct BomLine {
+    pub line: usize,
+    pub width_cm: f32,
+    pub height_cm: f32,
+    pub series: &'static str,
+    pub color: String,
+    pub items: Vec<BomItem>,
+}
+
+#[derive(Serialize)]
+pub struct Bom {
+    pub client: String,
+    pub date: String,
+    pub lines: Vec<BomLine>,
+    pub totals: Vec<BomItem>,
+}
+
+pub fn items(consumable: &Consumable) -> Vec<BomItem> {
+    let series = consumable.get_plise_name().label();
+    let color = consumable.profile_color();
+    let item = |item: &str, series, color: Option<&String>, quantity, unit| BomItem {
+        item: item.to_string(),
+        series,
+        color: color.cloned(),
+        quantity,
+        unit,
+    };
+
+    vec![
+        item(
+            "kasa",
+            Some(series),
+            Some(&color),
+            consumable.get_kasa_length_cm(),
+            "cm",
+        ),
+        item(
+            "kanat",
+            Some(series),
+            Some(&color),
+            consumable.get_kanat_length_cm(),
+            "cm",
+        ),
+        item(
+            consumable.tul.key(),
+            None,
+            None,
+            consumable.get_tul_cm_squared(),
+            "cm2",
+        ),
+        item("serit", None, None, consumable.get_serit_cm(), "cm"),
+        item(
+            "kose",
+            Some(series),
+            None,
+            consumable.get_kose_adet(),
+            "adet",
+        ),
+        item("teker", None, None, consumable.get_teker_adet(), "adet"),
+        item("klips", None, None, consumable.get_klips_adet(), "adet"),
+        item("stop", None, None, consumable.get_stop_adet(), "adet"),
+        item("donus", None, None, consumable.get_donus_adet(), "adet"),
+        item(
+            "adaptor",
+            None,
+            Some(&color),
+            consumable.get_adaptor_cm(),
+            "cm",
+        ),
+    ]
+}
+
+pub fn accessory_item(accessory: &Accessory, consumable: &Consumable) -> BomItem {
+    BomItem {
+        item: accessory.name.clone(),
+        series: None,
+        color: None,
+        quantity: accessory.quantity(consumable),
+        unit: accessory.unit(),
+    }
+}
+
+/// The add-ons chosen for a pleat.
+pub fn accessory_items(price: &Price, consumable: &Consumable) -> Vec<BomItem> {
+    price
+        .accessories_of(consumable)
+        .map(|accessory| accessory_item(accessory, consumable))
+        .collect()
+}
+
+impl Bom {
+    pub fn new(consumables: &[Consumable], item_count: u32, client: &str, price: &Price) -> Self {
+        let lines: Vec<BomLine> = consumables
+            .iter()
+            .take(item_count as usize)
+            .enumerate()
+            .map(|(i, consumable)| BomLine {
+                line: i + 1,
+                width_cm: consumable.width,
+                height_cm: consumable.height,
+                series: consumable.get_plise_name().label(),
+                color: consumable.profile_color(),
+                items: items(consumable)
+                    .into_iter()
+                    .chain(accessory_items(price, consumable))
+                    .collect(),
+            })
+            .collect();
+
+        let mut totals: Vec<BomItem> = Vec::new();
+        for item in lines.iter().flat_map(|line| &line.items) {
+            match totals.iter_mut().find(|total| {
+                total.item == item.item
+                    && total.series == item.series
+                    && total.color == item.color
+                    && total.unit == item.unit
+            }) {
+                Some(total) => total.quantity += item.quantity,
+                None => totals.push(item.clone()),
+            }
+        }
+        totals.retain(|total| total.quantity != 0.);
+
+        Bom {
+            client: sanitize_line(client),
+            date: Utc::now().format("%Y-%m-%d").to_string(),
+            lines,
+            totals,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, AppError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// One row per item; the order totals follow the pleats with `TOPLAM` in the line column.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("line,item,series,color,quantity,unit\n");
+        let rows = self
+            .lines
+            .iter()
+            .flat_map(|line| {
+                line.items
+                    .iter()
+                    .map(move |item| (line.line.to_string(), item))
+            })
+            .chain(self.totals.iter().map(|item| ("TOPLAM".to_string(), item)));
+        for (line, item) in rows {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                line,
+                escape_csv(&item.item),
+                escape_csv(item.series.unwrap_or("")),
+                escape_csv(item.color.as_deref().unwrap_or("")),
+                item.quantity,
+                item.unit
+            ));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plise::{ColorName, PliseName};
+
+    fn quantity(items: &[BomItem], name: &str) -> f32 {
+        items
+            .iter()
+            .find(|item| item.item == name)
+            .unwrap()
+            .quantity
+    }
+
+    #[test]
+    fn profile_lengths_match_the_cut_list() {
+        let mut consumable = Consumable::default();
+        consumable.set_plise_name(PliseName::Klasik);
+        let items = items(&consumable);
+        let cut = |prefix: &str| -> f32 {
+            consumable
+                .cut_list()
+                .iter()
+                .filter(|piece| piece.name.starts_with(prefix))
+                .map(|piece| piece.length_cm * piece.count as f32)
+                .sum()
+        };
+        assert_eq!(quantity(&items, "kasa"), 150.);
+        assert_eq!(quantity(&items, "kasa"), cut("Kasa"));
+        assert_eq!(quantity(&items, "kanat"), cut("Kanat"));
+
+        consumable.width = 180.;
+        assert_eq!(quantity(&super::items(&consumable), "kanat"), 2. * 32.);
+    }
+
+    #[test]
+    fn csv_declares_units() {
+        let bom = Bom::new(&[Consumable::default()], 1, "Ali, Veli", &Price::default());
+        let csv = bom.to_csv();
+        assert!(csv.starts_with("line,item,series,color,quantity,unit\n"));
+        assert!(csv.contains("1,kasa,Klasik,Beyaz,150,cm\n"));
+        assert!(csv.contains("TOPLAM,tul,,,1600,cm2\n"));
+        assert!(csv.contains("1,kose,Klasik,,4,adet\n"));
+    }
+
+    #[test]
+    fn custom_colour_profiles_are_totalled_per_code() {
+        let custom = |code: &str| {
+            let mut consumable = Consumable {
+                renk_kodu: code.to_string(),
+                ..Consumable::default()
+            };
+            consumable.set_plise_color(ColorName::Ozel);
+            consumable
+        };
+        let lines = [custom("RAL 7016"), custom("RAL 9005"), custom("ral 7016")];
+        let bom = Bom::new(&lines, 3, "", &Price::default());
+
+        assert_eq!(bom.lines[1].color, "Özel RAL RAL 9005");
+        let kasa: Vec<_> = bom
+            .totals
+            .iter()
+            .filter(|total| total.item == "kasa")
+            .map(|total| (total.color.as_deref().unwrap(), total.quantity))
+            .collect();
+        assert_eq!(
+            kasa,
+            [("Özel RAL RAL 7016", 300.), ("Özel RAL RAL 9005", 150.)]
+        );
+        assert!(bom
+            .to_csv()
+            .contains("TOPLAM,kasa,Klasik,Özel RAL RAL 9005,150,cm\n"));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::error::AppError;
+use crate::escape::{css_color, sanitize_line, sanitize_text};
+use crate::settings::{self, Versioned};
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::Path;
+
+/// Company details and colours printed on every generated document.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Brand {
+    pub company_name: String,
+    pub address: String,
+    pub phone: String,
+    pub email: String,
+    pub logo_path: String,
+    pub footer_terms: String,
+    pub bank_details: String,
+    pub header_color: String,
+    pub border_color: String,
+    pub text_color: String,
+}
+
+impl Default for Brand {
+    fn default() -> Self {
+        Brand {
+            company_name: "DOĞUŞ SİNEKLİK".to_string(),
+            address: "".to_string(),
+            phone: "".to_string(),
+            email: "".to_string(),
+            logo_path: "".to_string(),
+            footer_terms: "".to_string(),
+            bank_details: "".to_string(),
+            header_color: "#f2f2f2".to_string(),
+            border_color: "#000000".to_string(),
+            text_color: "#000000".to_string(),
+        }
+    }
+}
+
+impl Versioned for Brand {
+    const FILE_NAME: &'static str = "brand.json";
+    const VERSION: u32 = 1;
+}
+
+impl Brand {
+    pub fn create_from_file() -> Result<Self, AppError> {
+        settings::load(Self::FILE_NAME)
+    }
+
+    pub fn to_file(&self) -> Result<(), AppError> {
+        settings::save(Self::FILE_NAME, self)
+    }
+
+    /// A copy with every field cleaned for use in a document.
+    pub fn sanitized(&self) -> Brand {
+        let default = Brand::default();
+        Brand {
+            company_name: sanitize_line(&self.company_name),
+            address: sanitize_text(&self.address),
+            phone: sanitize_line(&self.phone),
+            email: sanitize_line(&self.email),
+            logo_path: sanitize_line(&self.logo_path),
+            footer_terms: sanitize_text(&self.footer_terms),
+            bank_details: sanitize_text(&self.bank_details),
+            header_color: css_color(&self.header_color, &default.header_color),
+            border_color: css_color(&self.border_color, &default.border_color),
+            text_color: css_color(&self.text_color, &default.text_color),
+        }
+    }
+
+    /// The logo as a `file://` URL the browser can load, or an empty string if no logo is set.
+    pub fn logo_url(&self) -> String {
+        if self.logo_path.trim().is_empty() {
+            return String::new();
+        }
+        let path = Path::new(self.logo_path.trim());
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            env::current_dir().unwrap_or_default().join(path)
+        };
+        format!(
+            "file:///{}",
+            path.display()
+                .to_string()
+                .replace('\\', "/")
+                .trim_start_matches('/')
+        )
+    }
+}
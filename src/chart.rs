@@ -0,0 +1,107 @@
+use eframe::egui;
+use egui::{Align2, Color32, FontId, Pos2, Sense, Shape, Stroke, Ui, Vec2};
+use std::f32::consts::TAU;
+
+/// Colours of the cost components, by their position in the breakdown: the legend,
+/// the segments of each bar and the pie slices all use colour `i` for component `i`,
+/// and the cost documents use the same ones.
+pub const PALETTE: [(u8, u8, u8); 10] = [
+    (31, 119, 180),
+    (255, 127, 14),
+    (44, 160, 44),
+    (214, 39, 40),
+    (148, 103, 189),
+    (140, 86, 75),
+    (227, 119, 194),
+    (127, 127, 127),
+    (188, 189, 34),
+    (23, 190, 207),
+];
+
+pub fn color(i: usize) -> Color32 {
+    let (r, g, b) = PALETTE[i % PALETTE.len()];
+    Color32::from_rgb(r, g, b)
+}
+
+pub fn legend(ui: &mut Ui, labels: &[&str]) {
+    ui.horizontal_wrapped(|ui| {
+        for (i, label) in labels.iter().enumerate() {
+            let (rect, _) = ui.allocate_exact_size(Vec2::splat(10.), Sense::hover());
+            ui.painter().rect_filled(rect, 0., color(i));
+            ui.label(*label);
+        }
+    });
+}
+
+/// One vertical bar per entry, each split into its values from the bottom up.
+pub fn stacked_bars(ui: &mut Ui, bars: &[(String, Vec<f32>)], height: f32) {
+    let max = bars
+        .iter()
+        .map(|(_, values)| values.iter().sum::<f32>())
+        .fold(0., f32::max);
+    let bar_width = 24.;
+    let gap = 8.;
+    let size = Vec2::new((bar_width + gap) * bars.len() as f32 + gap, height + 16.);
+    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let rect = response.rect;
+    let bottom = rect.top() + height;
+    painter.line_segment(
+        [
+            Pos2::new(rect.left(), bottom),
+            Pos2::new(rect.right(), bottom),
+        ],
+        Stroke::new(1., ui.visuals().text_color()),
+    );
+    if max <= 0. {
+        return;
+    }
+
+    for (i, (name, values)) in bars.iter().enumerate() {
+        let left = rect.left() + gap + i as f32 * (bar_width + gap);
+        let mut top = bottom;
+        for (j, value) in values.iter().enumerate() {
+            let h = value / max * height;
+            let segment = egui::Rect::from_min_max(
+                Pos2::new(left, top - h),
+                Pos2::new(left + bar_width, top),
+            );
+            painter.rect_filled(segment, 0., color(j));
+            top -= h;
+        }
+        painter.text(
+            Pos2::new(left + bar_width / 2., bottom + 2.),
+            Align2::CENTER_TOP,
+            name,
+            FontId::proportional(10.),
+            ui.visuals().text_color(),
+        );
+    }
+}
+
+/// Slices in proportion to the values, clockwise from twelve o'clock.
+pub fn pie(ui: &mut Ui, values: &[f32], radius: f32) {
+    let (response, painter) = ui.allocate_painter(Vec2::splat(radius * 2.), Sense::hover());
+    let center = response.rect.center();
+    let total: f32 = values.iter().sum();
+    if total <= 0. {
+        return;
+    }
+
+    let point = |angle: f32| center + radius * Vec2::new(angle.sin(), -angle.cos());
+    let mut start = 0.;
+    for (i, value) in values.iter().enumerate() {
+        let end = start + value / total * TAU;
+        // Drawn as narrow wedges, since each shape has to be convex.
+        let steps = ((end - start) / 0.1).ceil().max(1.) as usize;
+        for step in 0..steps {
+            let a = start + (end - start) * step as f32 / steps as f32;
+            let b = start + (end - start) * (step + 1) as f32 / steps as f32;
+            painter.add(Shape::convex_polygon(
+                vec![center, point(a), point(b)],
+                color(i),
+                Stroke::NONE,
+            ));
+        }
+        start = end;
+    }
+}
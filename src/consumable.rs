@@ -1,10 +1,25 @@
-use crate::plise::{ColorName, PliseName, PliseType};
+use crate::escape::sanitize_line;
+use crate::plise::{ColorName, MountType, PliseName, PliseType, TulType};
+use crate::survey::Survey;
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Consumable {
     pub plise_type: PliseType,
     pub width: f32,
     pub height: f32,
+    /// Where the pleat goes, e.g. "Salon", printed on its label for the installer.
+    pub oda: String,
+    /// RAL or wood-grain code, e.g. "RAL 7016".
+    pub renk_kodu: String,
+    pub tul: TulType,
+    pub montaj: MountType,
+    /// The rough opening the width and height were worked out from, if it was surveyed.
+    pub olcu: Option<Survey>,
+    /// Names of the add-ons chosen from `Price::accessories`.
+    pub aksesuarlar: Vec<String>,
 }
 
 impl Default for Consumable {
@@ -13,6 +28,12 @@ impl Default for Consumable {
             plise_type: PliseType::default(),
             width: 40.,
             height: 40.,
+            oda: "".to_string(),
+            renk_kodu: "".to_string(),
+            tul: TulType::default(),
+            montaj: MountType::default(),
+            olcu: None,
+            aksesuarlar: Vec::new(),
         }
     }
 }
@@ -23,6 +44,12 @@ impl Consumable {
             plise_type,
             width: width as f32,
             height: height as f32,
+            oda: "".to_string(),
+            renk_kodu: "".to_string(),
+            tul: TulType::default(),
+            montaj: MountType::default(),
+            olcu: None,
+            aksesuarlar: Vec::new(),
         }
     }
 
@@ -50,19 +77,73 @@ impl Consumable {
         self.get_plise_color() == color
     }
 
+    /// The RAL or wood-grain code with its spacing and case evened out, so "ral 7016"
+    /// and "RAL  7016" are the same colour.
+    pub fn color_code(&self) -> String {
+        self.renk_kodu
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_uppercase()
+    }
+
+    /// The profile colour as it is stocked and bought: the colour family, followed by
+    /// the code for custom RAL and wood-grain profiles, which are bought per code.
+    pub fn profile_color(&self) -> String {
+        let family = self.get_plise_color().label();
+        match self.get_plise_color() {
+            ColorName::Ozel | ColorName::Ahsap if !self.color_code().is_empty() => {
+                format!("{} {}", family, self.color_code())
+            }
+            _ => family.to_string(),
+        }
+    }
+
+    /// How much shorter than the pleat each kasa piece is cut. Face-fixed kasa is cut
+    /// to the full size, overlapping the opening.
+    fn kasa_deduction(&self) -> f32 {
+        match self.montaj {
+            MountType::Yuzey => 0.,
+            MountType::Pervaz | MountType::Adaptor => 2.5,
+        }
+    }
+
+    /// Total length of the kasa pieces in the cut list.
+    pub fn get_kasa_length_cm(&self) -> f32 {
+        2. * (self.width + self.height - 2. * self.kasa_deduction())
+    }
+
+    /// The kasa length weighted by the series' profile weight, which is what the metre
+    /// price is applied to. Not a length to cut or buy; see [`Self::get_kasa_length_cm`].
     pub fn get_kasa_cm(&self) -> f32 {
-        (2. * (self.width + self.height - 5.) * self.plise_type.get_kasa_weight()).round()
+        (self.get_kasa_length_cm() * self.plise_type.get_kasa_weight()).round()
     }
 
-    pub fn get_kanat_cm(&self) -> f32 {
-        let val = (self.height - 8.) * self.plise_type.get_kanat_weight();
-        if self.width < 150. {
-            val.round()
-        } else {
-            (2. * val).round()
+    /// The adapter profile goes all around the frame.
+    pub fn get_adaptor_cm(&self) -> f32 {
+        match self.montaj {
+            MountType::Adaptor => 2. * (self.width + self.height),
+            MountType::Pervaz | MountType::Yuzey => 0.,
         }
     }
 
+    /// Total length of the kanat pieces in the cut list.
+    pub fn get_kanat_length_cm(&self) -> f32 {
+        (self.height - 8.) * self.kanat_adet() as f32
+    }
+
+    /// The kanat length weighted by the series' profile weight, for costing like
+    /// [`Self::get_kasa_cm`].
+    pub fn get_kanat_cm(&self) -> f32 {
+        (self.get_kanat_length_cm() * self.plise_type.get_kanat_weight()).round()
+    }
+
+    /// Total length of the kasa, kanat and adapter pieces in the cut list, everything
+    /// that is painted.
+    pub fn get_profile_length_cm(&self) -> f32 {
+        self.get_kasa_length_cm() + self.get_kanat_length_cm() + self.get_adaptor_cm()
+    }
+
     pub fn get_tul_cm_squared(&self) -> f32 {
         self.width * self.height
     }
@@ -111,68 +192,122 @@ impl Consumable {
         }
     }
 
-    pub fn generate_wh_html_table(&self, idx: usize) -> String {
-        format!(
-            r#"
-    <tr>
-        <td>{}</td>
-        <td>{}x{}</td>
-        <td>{}</td>
-        <td>{}</td>
-    </tr>
-"#,
-            idx, self.width, self.height,
-            match self.get_plise_name() {
-                PliseName::Klasik => "Klasik",
-                PliseName::Genis => "Genis",
-                PliseName::Ince => "Ince",
+    /// One wing below 150 cm width, two above.
+    pub fn kanat_adet(&self) -> u32 {
+        if self.width < 150. {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The pieces to cut for one pleat. Kasa, kanat, şerit and the adapter profile add
+    /// up to the lengths the costing starts from, before the profile weights are applied.
+    pub fn cut_list(&self) -> Vec<CutPiece> {
+        let wings = self.kanat_adet();
+        let mut cuts = vec![
+            CutPiece {
+                name: "Kasa Yatay",
+                length_cm: self.width - self.kasa_deduction(),
+                width_cm: None,
+                count: 2,
             },
-            match self.get_plise_color() {
-                ColorName::Beyaz => "Beyaz",
-                ColorName::Boya => "Boya",
-                ColorName::Ahsap => "Ahsap",
+            CutPiece {
+                name: "Kasa Dikey",
+                length_cm: self.height - self.kasa_deduction(),
+                width_cm: None,
+                count: 2,
             },
-        )
-    }
-
-    pub fn generate_html_table(&self, idx: usize) -> String {
-        format!(
-            r#"
-    <tr>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-        <td>{}</td>
-    </tr>
-"#,
-            idx,
-            match self.get_plise_name() {
-                PliseName::Klasik => "Klasik",
-                PliseName::Genis => "Genis",
-                PliseName::Ince => "Ince",
+            CutPiece {
+                name: "Kanat",
+                length_cm: self.height - 8.,
+                width_cm: None,
+                count: wings,
+            },
+            CutPiece {
+                name: "Şerit",
+                length_cm: self.height - 5.,
+                width_cm: None,
+                count: 2 * wings,
             },
-            match self.get_plise_color() {
-                ColorName::Beyaz => "Beyaz",
-                ColorName::Boya => "Boya",
-                ColorName::Ahsap => "Ahsap",
+            CutPiece {
+                name: self.tul.label(),
+                length_cm: self.height,
+                width_cm: Some(self.width / wings as f32),
+                count: wings,
             },
-            self.get_kasa_cm(),
-            self.get_kanat_cm(),
-            self.get_tul_cm_squared(),
-            self.get_serit_cm(),
-            self.get_kose_adet(),
-            self.get_teker_adet(),
-            self.get_klips_adet(),
-            self.get_stop_adet(),
-            self.get_donus_adet(),
-        )
+        ];
+        if self.montaj == MountType::Adaptor {
+            cuts.push(CutPiece {
+                name: "Adaptör Yatay",
+                length_cm: self.width,
+                width_cm: None,
+                count: 2,
+            });
+            cuts.push(CutPiece {
+                name: "Adaptör Dikey",
+                length_cm: self.height,
+                width_cm: None,
+                count: 2,
+            });
+        }
+        cuts
     }
+
+    pub fn row(&self, idx: usize) -> ConsumableRow {
+        ConsumableRow {
+            idx,
+            width: self.width,
+            height: self.height,
+            plise_name: self.get_plise_name().label(),
+            color: self.get_plise_color().label(),
+            renk_kodu: sanitize_line(&self.renk_kodu),
+            tul: self.tul.label(),
+            montaj: self.montaj.label(),
+            kasa_cm: self.get_kasa_cm(),
+            kanat_cm: self.get_kanat_cm(),
+            tul_cm_squared: self.get_tul_cm_squared(),
+            serit_cm: self.get_serit_cm(),
+            kose_adet: self.get_kose_adet(),
+            teker_adet: self.get_teker_adet(),
+            klips_adet: self.get_klips_adet(),
+            stop_adet: self.get_stop_adet(),
+            donus_adet: self.get_donus_adet(),
+            adaptor_cm: self.get_adaptor_cm(),
+            aksesuarlar: sanitize_line(&self.aksesuarlar.join(", ")),
+        }
+    }
+}
+
+/// One line of a generated document, as handed to the document templates.
+#[derive(Clone, Serialize)]
+pub struct ConsumableRow {
+    pub idx: usize,
+    pub width: f32,
+    pub height: f32,
+    pub plise_name: &'static str,
+    pub color: &'static str,
+    pub renk_kodu: String,
+    pub tul: &'static str,
+    pub montaj: &'static str,
+    pub kasa_cm: f32,
+    pub kanat_cm: f32,
+    pub tul_cm_squared: f32,
+    pub serit_cm: f32,
+    pub kose_adet: f32,
+    pub teker_adet: f32,
+    pub klips_adet: f32,
+    pub stop_adet: f32,
+    pub donus_adet: f32,
+    pub adaptor_cm: f32,
+    pub aksesuarlar: String,
+}
+
+#[derive(Serialize)]
+pub struct CutPiece {
+    pub name: &'static str,
+    pub length_cm: f32,
+    /// Only for the mesh, which is cut to an area.
+    pub width_cm: Option<f32>,
+    pub count: u32,
 }
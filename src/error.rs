@@ -0,0 +1,167 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can go wrong while loading or saving settings and while generating
+/// documents. The messages are shown to the user in the error window.
+#[derive(Debug)]
+pub enum AppError {
+    /// A settings file could not be read or written.
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A settings file exists but is not valid JSON for its type.
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A settings file was written by a newer version of the program.
+    Version {
+        path: PathBuf,
+        version: u32,
+    },
+    /// A generated document could not be written.
+    Document {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Labels could not be sent to the label printer.
+    Printer {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// The document was written but could not be opened in the browser.
+    Browser {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// An imported price list failed its checks and was not used.
+    PriceList {
+        path: PathBuf,
+        reason: &'static str,
+    },
+    Template(tera::Error),
+    Xlsx(rust_xlsxwriter::XlsxError),
+    Ods(zip::result::ZipError),
+    Json(serde_json::Error),
+    Database(rusqlite::Error),
+    Qr(qrcode::types::QrError),
+    Pdf(&'static str),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Io { path, source } => {
+                write!(f, "{} dosyasına erişilemedi: {}", path.display(), source)
+            }
+            AppError::Parse { path, source } => write!(
+                f,
+                "{} okunamadı, dosya bozuk olabilir: {}",
+                path.display(),
+                source
+            ),
+            AppError::Version { path, version } => write!(
+                f,
+                "{} programın daha yeni bir sürümüyle kaydedilmiş (sürüm {})",
+                path.display(),
+                version
+            ),
+            AppError::Document { path, source } => {
+                write!(f, "{} kaydedilemedi: {}", path.display(), source)
+            }
+            AppError::Printer { path, source } => write!(
+                f,
+                "Etiketler yazıcıya ({}) gönderilemedi: {}",
+                path.display(),
+                source
+            ),
+            AppError::Browser { path, source } => write!(
+                f,
+                "{} kaydedildi ancak tarayıcıda açılamadı: {}",
+                path.display(),
+                source
+            ),
+            AppError::PriceList { path, reason } => {
+                write!(
+                    f,
+                    "{} fiyat listesi kabul edilmedi: {}",
+                    path.display(),
+                    reason
+                )
+            }
+            AppError::Template(e) => {
+                write!(f, "Belge şablonu işlenemedi: {}", e)?;
+                // Tera keeps the useful part of the message (line, variable) in the source chain.
+                let mut source = std::error::Error::source(e);
+                while let Some(e) = source {
+                    write!(f, ": {}", e)?;
+                    source = e.source();
+                }
+                Ok(())
+            }
+            AppError::Xlsx(e) => write!(f, "Excel dosyası oluşturulamadı: {}", e),
+            AppError::Ods(e) => write!(f, "ODS dosyası oluşturulamadı: {}", e),
+            AppError::Json(e) => write!(f, "JSON oluşturulamadı: {}", e),
+            AppError::Database(e) => write!(f, "Veritabanı hatası: {}", e),
+            AppError::Qr(e) => write!(f, "QR kodu oluşturulamadı: {}", e),
+            AppError::Pdf(reason) => write!(f, "PDF oluşturulamadı: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Io { source, .. }
+            | AppError::Document { source, .. }
+            | AppError::Printer { source, .. }
+            | AppError::Browser { source, .. } => Some(source),
+            AppError::Parse { source, .. } => Some(source),
+            AppError::Version { .. } | AppError::PriceList { .. } | AppError::Pdf(_) => None,
+            AppError::Template(e) => Some(e),
+            AppError::Xlsx(e) => Some(e),
+            AppError::Ods(e) => Some(e),
+            AppError::Json(e) => Some(e),
+            AppError::Database(e) => Some(e),
+            AppError::Qr(e) => Some(e),
+        }
+    }
+}
+
+impl From<tera::Error> for AppError {
+    fn from(e: tera::Error) -> Self {
+        AppError::Template(e)
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for AppError {
+    fn from(e: rust_xlsxwriter::XlsxError) -> Self {
+        AppError::Xlsx(e)
+    }
+}
+
+impl From<zip::result::ZipError> for AppError {
+    fn from(e: zip::result::ZipError) -> Self {
+        AppError::Ods(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Json(e)
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Database(e)
+    }
+}
+
+impl From<qrcode::types::QrError> for AppError {
+    fn from(e: qrcode::types::QrError) -> Self {
+        AppError::Qr(e)
+    }
+}
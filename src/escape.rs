@@ -0,0 +1,170 @@
+//! Escaping and sanitising of user-entered text before it reaches a document or a file name.
+
+/// Escapes the characters that are significant in HTML text and attribute values.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Bidirectional override and isolate characters can make a name render differently
+/// from what was typed, so they are dropped along with control characters.
+fn is_unwanted(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+        || c == '\u{FEFF}'
+}
+
+/// Cleans a single-line field such as a client name: control characters are removed,
+/// runs of whitespace collapse into one space and the ends are trimmed.
+pub fn sanitize_line(s: &str) -> String {
+    s.split(|c: char| c.is_whitespace() || c.is_control())
+        .map(|word| {
+            word.chars()
+                .filter(|c| !is_unwanted(*c))
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Cleans a single-line field for a ZPL `^FD` command used with `^FH_`: the characters
+/// ZPL treats as commands, and the escape character itself, are written as hex.
+pub fn zpl_field(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in sanitize_line(s).chars() {
+        match c {
+            '_' => escaped.push_str("_5F"),
+            '^' => escaped.push_str("_5E"),
+            '~' => escaped.push_str("_7E"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Cleans a multi-line field such as an address, keeping the line breaks.
+pub fn sanitize_text(s: &str) -> String {
+    s.lines()
+        .map(sanitize_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Accepts `#rgb`/`#rrggbb` and plain colour names, anything else falls back to `default`
+/// so a colour setting can never break out of its CSS declaration.
+pub fn css_color(s: &str, default: &str) -> String {
+    let s = s.trim();
+    let valid = match s.strip_prefix('#') {
+        Some(hex) => {
+            (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => !s.is_empty() && s.chars().all(|c| c.is_ascii_alphabetic()),
+    };
+    if valid {
+        s.to_string()
+    } else {
+        default.to_string()
+    }
+}
+
+/// Replaces characters that are not allowed in file names on Windows or Linux.
+pub fn file_name_safe(s: &str) -> String {
+    sanitize_line(s)
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_matches('.')
+        .to_string()
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break.
+pub fn escape_csv(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_html_escapes_markup_and_keeps_unicode() {
+        assert_eq!(
+            escape_html("<script>&\"'İı😀"),
+            "&lt;script&gt;&amp;&quot;&#39;İı😀"
+        );
+        assert_eq!(escape_html("Işık Ağaç"), "Işık Ağaç");
+    }
+
+    #[test]
+    fn sanitize_line_strips_bidi_and_control_characters() {
+        assert_eq!(sanitize_line("  Ali\u{202E}Veli\u{2066}  "), "AliVeli");
+        assert_eq!(sanitize_line("Ali\tVeli\r\n\u{0007}Usta"), "Ali Veli Usta");
+        assert_eq!(
+            sanitize_line("\u{FEFF}İpek\u{200F} ıhlamur 😀"),
+            "İpek ıhlamur 😀"
+        );
+    }
+
+    #[test]
+    fn sanitize_text_keeps_line_breaks() {
+        assert_eq!(
+            sanitize_text("\n Atatürk Cd.  12\u{202E}\r\nİzmir \n"),
+            "Atatürk Cd. 12\nİzmir"
+        );
+    }
+
+    #[test]
+    fn zpl_field_hex_escapes_commands() {
+        assert_eq!(zpl_field("^XZ~JA_İ"), "_5EXZ_7EJA_5Fİ");
+    }
+
+    #[test]
+    fn css_color_rejects_anything_but_hex_and_names() {
+        assert_eq!(css_color(" #A0b ", "#000000"), "#A0b");
+        assert_eq!(css_color("#f2f2f2", "#000000"), "#f2f2f2");
+        assert_eq!(css_color("navy", "#000000"), "navy");
+        assert_eq!(css_color("red;}</style><script>", "#000000"), "#000000");
+        assert_eq!(css_color("#12345", "#000000"), "#000000");
+        assert_eq!(css_color("#ggg", "#000000"), "#000000");
+        assert_eq!(css_color("", "#000000"), "#000000");
+    }
+
+    #[test]
+    fn file_name_safe_replaces_reserved_characters() {
+        assert_eq!(
+            file_name_safe("a<b>c:d\"e/f\\g|h?i*j"),
+            "a_b_c_d_e_f_g_h_i_j"
+        );
+        assert_eq!(file_name_safe("..\\..\\Şişli Ofis.."), "_.._Şişli_Ofis");
+        assert_eq!(file_name_safe("İı😀\u{202E}"), "İı😀");
+    }
+
+    #[test]
+    fn escape_csv_quotes_only_when_needed() {
+        assert_eq!(escape_csv("Klasik Beyaz"), "Klasik Beyaz");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("12\" kulp"), "\"12\"\" kulp\"");
+        assert_eq!(escape_csv("iki\nsatır"), "\"iki\nsatır\"");
+        assert_eq!(escape_csv("İı😀"), "İı😀");
+    }
+}
@@ -1,134 +1,429 @@
+use crate::brand::Brand;
+use crate::chart::PALETTE;
+use crate::consumable::ConsumableRow;
+use crate::error::AppError;
+use crate::escape::{escape_html, sanitize_line};
+use crate::label::{Label, LabelSize};
+use crate::price::CostBreakdown;
+use crate::purchase::{PartComparison, PurchaseOrder};
+use crate::settings;
+use crate::work_order::{self, WorkOrderLine};
+
 use chrono::prelude::Utc;
-use std::fs::File;
-use std::io::Write;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::Path;
+use tera::{Context, Tera};
+
+const BASE_TEMPLATE: &str = include_str!("../templates/base.html");
+const CONSUMABLES_TEMPLATE: &str = include_str!("../templates/maliyet.html");
+const PRICE_TEMPLATE: &str = include_str!("../templates/fiyat.html");
+const WORK_ORDER_TEMPLATE: &str = include_str!("../templates/is_emri.html");
+const LABEL_TEMPLATE: &str = include_str!("../templates/etiket.html");
+const PURCHASE_ORDER_TEMPLATE: &str = include_str!("../templates/satin_alma.html");
+const SOURCE_COMPARISON_TEMPLATE: &str = include_str!("../templates/tedarik.html");
 
 pub struct Html {}
 
+#[derive(Serialize)]
+struct BreakdownContext {
+    labels: Vec<&'static str>,
+    lines: Vec<BreakdownLine>,
+    totals: Vec<String>,
+    total: String,
+    shares: Vec<Share>,
+}
+
+#[derive(Serialize)]
+struct BreakdownLine {
+    idx: usize,
+    values: Vec<String>,
+    total: String,
+}
+
+/// A component's part of the total cost, for the bar under the table.
+#[derive(Serialize)]
+struct Share {
+    label: &'static str,
+    percent: String,
+    color: String,
+}
+
+#[derive(Serialize)]
+struct PurchaseLineContext {
+    name: String,
+    part_no: String,
+    needed: String,
+    packs: u32,
+    pack_size: String,
+    quantity: String,
+    unit: &'static str,
+    unit_price: String,
+    total: String,
+}
+
+#[derive(Serialize)]
+struct PartComparisonContext {
+    label: &'static str,
+    needed: String,
+    unit: &'static str,
+    sources: Vec<SourceComparisonContext>,
+}
+
+#[derive(Serialize)]
+struct SourceComparisonContext {
+    supplier: String,
+    part_no: String,
+    price: String,
+    difference: String,
+    pack_size: f32,
+    min_quantity: f32,
+    lead_days: u32,
+    order_total: Option<String>,
+    preferred: bool,
+    cheapest: bool,
+}
+
+impl BreakdownContext {
+    fn new(breakdown: &CostBreakdown) -> Self {
+        let money = |value: &f32| format!("{:.2}", value);
+        BreakdownContext {
+            labels: breakdown.labels.clone(),
+            lines: breakdown
+                .lines
+                .iter()
+                .enumerate()
+                .map(|(i, values)| BreakdownLine {
+                    idx: i + 1,
+                    values: values.iter().map(money).collect(),
+                    total: money(&values.iter().sum()),
+                })
+                .collect(),
+            totals: breakdown.totals.iter().map(money).collect(),
+            total: money(&breakdown.total),
+            shares: breakdown
+                .labels
+                .iter()
+                .zip(&breakdown.totals)
+                .enumerate()
+                .filter(|(_, (_, value))| **value > 0.)
+                .map(|(i, (label, value))| {
+                    let (r, g, b) = PALETTE[i % PALETTE.len()];
+                    Share {
+                        label,
+                        percent: format!("{:.1}", value / breakdown.total * 100.),
+                        color: format!("#{:02x}{:02x}{:02x}", r, g, b),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
 impl Html {
-    pub fn wrap_with_skeleton(s: &str) -> String {
-        format!(
-            r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <title>Plise Measurements</title>
-    <style>
-        table {{
-            width: 100%;
-            border-collapse: collapse;
-        }}
-        th, td {{
-            border: 1px solid black;
-            padding: 8px;
-            text-align: left;
-        }}
-        th {{
-            background-color: #f2f2f2;
-        }}
-    </style>
-</head>
-<body>
-{}
-</body>
-</html>"#,
-            s
-        )
+    /// Loads the document templates. A file with the same name in the `templates`
+    /// folder of the data folder replaces the built-in template.
+    fn templates() -> Result<Tera, AppError> {
+        let mut tera = Tera::default();
+        tera.autoescape_on(vec![".html"]);
+        tera.set_escape_fn(escape_html);
+        tera.add_raw_templates(vec![
+            (
+                "base.html",
+                Html::load_template("base.html", BASE_TEMPLATE)?,
+            ),
+            (
+                "maliyet.html",
+                Html::load_template("maliyet.html", CONSUMABLES_TEMPLATE)?,
+            ),
+            (
+                "fiyat.html",
+                Html::load_template("fiyat.html", PRICE_TEMPLATE)?,
+            ),
+            (
+                "is_emri.html",
+                Html::load_template("is_emri.html", WORK_ORDER_TEMPLATE)?,
+            ),
+            (
+                "etiket.html",
+                Html::load_template("etiket.html", LABEL_TEMPLATE)?,
+            ),
+            (
+                "satin_alma.html",
+                Html::load_template("satin_alma.html", PURCHASE_ORDER_TEMPLATE)?,
+            ),
+            (
+                "tedarik.html",
+                Html::load_template("tedarik.html", SOURCE_COMPARISON_TEMPLATE)?,
+            ),
+        ])?;
+        Ok(tera)
     }
 
-    pub fn create_consumables_html(s: &str, maliyet: f32, client: &str) {
-        let date = Utc::now();
-        let table = format!(
-            r#"
-<table>
-    <tr>
-        <th colspan="12", style="text-align: center;">DOĞUŞ SİNEKLİK SARF MALZEME TABLOSU</th>
-    </tr>
-    <tr>
-        <th style="border-right: none;">Müşteri Adı:</th>
-        <th style="border-left: none;">{}</th>
-        <th colspan="8"></th>
-        <th style="border-right: none;">Tarih:</th>
-        <th style="border-left: none;">{}</th>
-    </tr>
-    <tr>
-        <th>Sıra</th>
-        <th>Plise Tipi</th>
-        <th>Renk Tipi</th>
-        <th>Kasa (cm)</th>
-        <th>Kanat (cm)</th>
-        <th>Tül (cm^2)</th>
-        <th>Şerit (cm)</th>
-        <th>Köşe Adet</th>
-        <th>Teker Adet</th>
-        <th>Klips Adet</th>
-        <th>Stop Adet</th>
-        <th>Dönüş Adet</th>
-    </tr>
-{}
-<tr>
-<td colspan="10"></td>
-<td style="border-right: none;">Toplam Maliyet Fiyatı:</td>
-<td style="border-left: none;">{:.2} TL</td>
-</tr>
-</table>
-"#,
-            client,
-            date.format("%d.%m.%Y"),
-            s,
-            maliyet
-        );
+    fn load_template(name: &str, built_in: &str) -> Result<String, AppError> {
+        let mut path = settings::settings_path("templates");
+        path.push(name);
+        if !path.exists() {
+            return Ok(built_in.to_string());
+        }
+        fs::read_to_string(&path).map_err(|source| AppError::Io { path, source })
+    }
 
-        let content = Html::wrap_with_skeleton(&table);
-        let mut file = File::create("maliyet.html").unwrap();
-        file.write_all(content.as_bytes()).unwrap();
-        webbrowser::open("maliyet.html").unwrap();
-    }
-
-    pub fn create_price_html(s: &str, price: f32, kdv: f32, client: &str) {
-        let date = Utc::now();
-        let table = format!(
-            r#"
-<h2>Plise Fiyat Tablosu</h2>
-<table>
-    <tr>
-        <th colspan="7", style="text-align: center;">DOĞUŞ SİNEKLİK ÜCRET TABLOSU</th>
-    </tr>
-    <tr>
-        <th>Müşteri Adı: {}</th>
-        <th colspan="2"></th>
-        <th style="text-align: right;">Tarih: {}</th>
-    </tr>
-    <tr>
-        <th>Sıra</th>
-        <th>Plise Ölçüsü</th>
-        <th>Plise Tipi</th>
-        <th>Boya Tipi</th>
-    </tr>
-{}
-<tr>
-<td style="border-bottom: none;" colspan="3"></td>
-<td>Toplam Fiyat: {:.2} TL</td>
-</tr>
-<tr>
-<td style="border-bottom: none; border-top: none;" colspan="3"></td>
-<td>KDV (%) {}</td>
-</tr>
-<tr>
-<td style="border-top: none;" colspan="3"></td>
-<td>Kdv Dahil Fiyat: {:.2}</td>
-</tr>
-</table>
-"#,
-            client,
-            date.format("%d.%m.%Y"),
-            s,
-            price, kdv, price * (1.0 + kdv / 100.0)
+    /// Every user-entered value goes through `sanitize_*` here and is HTML-escaped by
+    /// the template engine, so templates must not mark these values `safe`.
+    fn context(brand: &Brand, client: &str, document_number: u32) -> Context {
+        let brand = brand.sanitized();
+        let mut context = Context::new();
+        context.insert("brand", &brand);
+        context.insert("logo_url", &brand.logo_url());
+        context.insert("client", &sanitize_line(client));
+        context.insert("document_number", &format!("{:04}", document_number));
+        context.insert("date", &Utc::now().format("%d.%m.%Y").to_string());
+        context
+    }
+
+    /// `breakdown` adds a table and a bar of the cost per component.
+    pub fn create_consumables_html(
+        rows: &[ConsumableRow],
+        maliyet: f32,
+        breakdown: Option<&CostBreakdown>,
+        client: &str,
+        document_number: u32,
+        brand: &Brand,
+    ) -> Result<String, AppError> {
+        let mut context = Html::context(brand, client, document_number);
+        context.insert("rows", rows);
+        context.insert("maliyet", &format!("{:.2}", maliyet));
+        if let Some(breakdown) = breakdown {
+            context.insert("breakdown", &BreakdownContext::new(breakdown));
+        }
+
+        Ok(Html::templates()?.render("maliyet.html", &context)?)
+    }
+
+    pub fn create_price_html(
+        rows: &[ConsumableRow],
+        price: f32,
+        kdv: f32,
+        delivery_date: Option<&str>,
+        client: &str,
+        document_number: u32,
+        brand: &Brand,
+    ) -> Result<String, AppError> {
+        let mut context = Html::context(brand, client, document_number);
+        context.insert("rows", rows);
+        context.insert("delivery_date", &delivery_date);
+        context.insert("total_price", &format!("{:.2}", price));
+        context.insert("kdv", &kdv);
+        context.insert(
+            "total_price_kdv",
+            &format!("{:.2}", price * (1.0 + kdv / 100.0)),
         );
 
-        let content = Html::wrap_with_skeleton(&table);
-        let mut file = File::create("fiyat.html").unwrap();
-        file.write_all(content.as_bytes()).unwrap();
+        Ok(Html::templates()?.render("fiyat.html", &context)?)
+    }
+
+    /// One sheet per pleat when `per_pleat` is set, otherwise the whole batch on one sheet.
+    pub fn create_work_order_html(
+        lines: &[WorkOrderLine],
+        per_pleat: bool,
+        delivery_date: Option<&str>,
+        client: &str,
+        document_number: u32,
+        brand: &Brand,
+    ) -> Result<String, AppError> {
+        let mut context = Html::context(brand, client, document_number);
+        context.insert("lines", lines);
+        context.insert("delivery_date", &delivery_date);
+        context.insert("per_pleat", &per_pleat);
+        context.insert("order_code", &work_order::order_code(document_number));
+
+        Ok(Html::templates()?.render("is_emri.html", &context)?)
+    }
+
+    /// One label per page, the page sized to the label for printing or saving as PDF.
+    pub fn create_label_html(
+        labels: &[Label],
+        size: LabelSize,
+        client: &str,
+        document_number: u32,
+        brand: &Brand,
+    ) -> Result<String, AppError> {
+        let labels: Vec<Label> = labels
+            .iter()
+            .map(|label| Label {
+                oda: sanitize_line(&label.oda),
+                ..label.clone()
+            })
+            .collect();
+        let (width, height) = size.mm();
+        let mut context = Html::context(brand, client, document_number);
+        context.insert("labels", &labels);
+        context.insert("width_mm", &width);
+        context.insert("height_mm", &height);
+
+        Ok(Html::templates()?.render("etiket.html", &context)?)
+    }
+
+    /// The supplier's name takes the place of the client.
+    pub fn create_purchase_order_html(
+        order: &PurchaseOrder,
+        document_number: u32,
+        brand: &Brand,
+    ) -> Result<String, AppError> {
+        let quantity = |value: f32| format!("{}", (value * 100.).round() / 100.);
+        let lines: Vec<PurchaseLineContext> = order
+            .lines
+            .iter()
+            .map(|line| PurchaseLineContext {
+                name: sanitize_line(&line.name),
+                part_no: sanitize_line(&line.part_no),
+                needed: quantity(line.needed),
+                packs: line.packs,
+                pack_size: quantity(line.pack_size),
+                quantity: quantity(line.quantity),
+                unit: line.unit,
+                unit_price: format!("{:.2}", line.unit_price),
+                total: format!("{:.2}", line.total),
+            })
+            .collect();
+        let mut context = Html::context(brand, &order.supplier, document_number);
+        context.insert("lines", &lines);
+        context.insert("total", &format!("{:.2}", order.total));
+        context.insert("order_code", &format!("SA-{:04}", document_number));
+        context.insert("lead_days", &order.lead_days);
+
+        Ok(Html::templates()?.render("satin_alma.html", &context)?)
+    }
+
+    pub fn create_source_comparison_html(
+        comparisons: &[PartComparison],
+        document_number: u32,
+        brand: &Brand,
+    ) -> Result<String, AppError> {
+        let parts: Vec<PartComparisonContext> = comparisons
+            .iter()
+            .map(|part| PartComparisonContext {
+                label: part.label,
+                needed: format!("{:.2}", part.needed),
+                unit: part.unit,
+                sources: part
+                    .sources
+                    .iter()
+                    .map(|source| SourceComparisonContext {
+                        supplier: sanitize_line(&source.supplier),
+                        part_no: sanitize_line(&source.part_no),
+                        price: format!("{:.2}", source.price),
+                        difference: format!("{:+.1}", source.difference),
+                        pack_size: source.pack_size,
+                        min_quantity: source.min_quantity,
+                        lead_days: source.lead_days,
+                        order_total: source.order_total.map(|total| format!("{:.2}", total)),
+                        preferred: source.preferred,
+                        cheapest: source.cheapest,
+                    })
+                    .collect(),
+            })
+            .collect();
+        let mut context = Html::context(brand, "", document_number);
+        context.insert("parts", &parts);
+
+        Ok(Html::templates()?.render("tedarik.html", &context)?)
+    }
+
+    pub fn open(path: &Path) -> Result<(), AppError> {
+        let path = env::current_dir().unwrap_or_default().join(path);
+        webbrowser::open(&path.to_string_lossy())
+            .map_err(|source| AppError::Browser { path, source })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consumable::Consumable;
+
+    const HOSTILE: &str = "<script>&\"'İı😀";
+    const ESCAPED: &str = "&lt;script&gt;&amp;&quot;&#39;İı😀";
+
+    fn rows() -> Vec<ConsumableRow> {
+        let consumable = Consumable {
+            oda: HOSTILE.to_string(),
+            renk_kodu: format!("RAL\u{202E} {}", HOSTILE),
+            ..Consumable::default()
+        };
+        vec![consumable.row(1)]
+    }
+
+    fn hostile_brand() -> Brand {
+        Brand {
+            company_name: format!("{}\u{202E}", HOSTILE),
+            address: format!("<b>Atatürk Cd.</b>\n{}", HOSTILE),
+            phone: "\u{2066}0 212 555 00 00".to_string(),
+            email: "a@b.com\"><img src=x onerror=alert(1)>".to_string(),
+            footer_terms: HOSTILE.to_string(),
+            bank_details: HOSTILE.to_string(),
+            header_color: "red;}</style><script>alert(1)</script>".to_string(),
+            border_color: "#000;background:url(x)".to_string(),
+            text_color: "#123".to_string(),
+            ..Brand::default()
+        }
+    }
+
+    fn assert_escaped(html: &str) {
+        assert!(html.contains(ESCAPED), "{}", html);
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<img src=x"));
+        assert!(!html.contains("<b>Atatürk"));
+        assert!(!html.contains('\u{202E}'));
+        assert!(!html.contains('\u{2066}'));
+        assert!(html.contains("&lt;b&gt;Atatürk Cd.&lt;/b&gt;"));
+        assert!(html.contains("background-color: #f2f2f2;"));
+        assert!(html.contains("border: 1px solid #000000;"));
+        assert!(html.contains("color: #123;"));
+    }
+
+    #[test]
+    fn price_document_escapes_client_and_brand() {
+        settings::init_test_data_dir();
+        let html = Html::create_price_html(
+            &rows(),
+            100.,
+            20.,
+            Some("01.01.2025"),
+            HOSTILE,
+            7,
+            &hostile_brand(),
+        )
+        .unwrap();
+        assert_escaped(&html);
+        assert!(html.contains(&format!("Müşteri Adı: {}", ESCAPED)));
+        assert!(html.contains(&format!("RAL {}", ESCAPED)));
+    }
+
+    #[test]
+    fn consumables_document_escapes_client_and_brand() {
+        settings::init_test_data_dir();
+        let html = Html::create_consumables_html(&rows(), 100., None, HOSTILE, 7, &hostile_brand())
+            .unwrap();
+        assert_escaped(&html);
+        assert!(html.contains(&format!(
+            "<th style=\"border-left: none;\">{}</th>",
+            ESCAPED
+        )));
+    }
 
-        webbrowser::open("fiyat.html").unwrap();
+    #[test]
+    fn unicode_names_come_through_unchanged() {
+        settings::init_test_data_dir();
+        let brand = Brand {
+            company_name: "IŞIK İPLİK ıhlamur 😀".to_string(),
+            ..Brand::default()
+        };
+        let html =
+            Html::create_price_html(&rows(), 1., 20., None, "Şükrü Öztürk 😀", 1, &brand).unwrap();
+        assert!(html.contains("IŞIK İPLİK ıhlamur 😀"));
+        assert!(html.contains("Şükrü Öztürk 😀"));
     }
 }
@@ -0,0 +1,378 @@
+use crate::consumable::{Consumable, ConsumableRow};
+use crate::error::AppError;
+use crate::escape::{sanitize_line, zpl_field};
+use crate::pdf::{self, Page};
+use crate::work_order::{order_code, qr_svg};
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use qrcode::{Color, QrCode};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Common label sizes for thermal and sheet printers.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LabelSize {
+    /// 58 x 40 mm
+    Kucuk,
+    /// 100 x 50 mm
+    Orta,
+    /// 100 x 150 mm, shipping label
+    Buyuk,
+}
+
+impl LabelSize {
+    pub const ALL: [LabelSize; 3] = [LabelSize::Kucuk, LabelSize::Orta, LabelSize::Buyuk];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LabelSize::Kucuk => "58 x 40 mm",
+            LabelSize::Orta => "100 x 50 mm",
+            LabelSize::Buyuk => "100 x 150 mm",
+        }
+    }
+
+    /// Width and height in millimetres.
+    pub fn mm(&self) -> (u32, u32) {
+        match self {
+            LabelSize::Kucuk => (58, 40),
+            LabelSize::Orta => (100, 50),
+            LabelSize::Buyuk => (100, 150),
+        }
+    }
+}
+
+/// The label of one finished pleat.
+#[derive(Clone, Serialize)]
+pub struct Label {
+    pub row: ConsumableRow,
+    pub count: usize,
+    pub oda: String,
+    /// The same code as on the work order, e.g. `IE-0012-03`, also encoded in the QR code.
+    pub code: String,
+    pub qr_svg: String,
+}
+
+pub fn labels(
+    consumables: &[Consumable],
+    item_count: u32,
+    document_number: u32,
+) -> Result<Vec<Label>, AppError> {
+    let count = item_count as usize;
+    consumables
+        .iter()
+        .take(count)
+        .enumerate()
+        .map(|(i, consumable)| {
+            let code = format!("{}-{:02}", order_code(document_number), i + 1);
+            Ok(Label {
+                row: consumable.row(i + 1),
+                count,
+                oda: consumable.oda.clone(),
+                qr_svg: qr_svg(&code)?,
+                code,
+            })
+        })
+        .collect()
+}
+
+/// Printer resolution of 203 dpi, the most common one for thermal label printers.
+const DOTS_PER_MM: u32 = 8;
+
+/// One ZPL label per pleat, text on the left and the QR code on the right.
+pub fn zpl(labels: &[Label], client: &str, size: LabelSize) -> String {
+    let (width, height) = size.mm();
+    let (width, height) = (width * DOTS_PER_MM, height * DOTS_PER_MM);
+    let margin = 3 * DOTS_PER_MM;
+    // A version 1 QR code is 21 modules wide, plus the quiet zone ^BQ leaves around it.
+    let magnification = ((height - 2 * margin).min(width / 3) / 25).clamp(1, 10);
+    let qr_x = width - margin - 25 * magnification;
+    let text_width = qr_x - 2 * margin;
+    let line_height = (height - 2 * margin) / 5;
+    // Room for about 18 characters, enough for the line code and its position.
+    let font = (line_height * 4 / 5).min(text_width / 10);
+
+    let mut zpl = String::new();
+    for label in labels {
+        let lines = [
+            zpl_field(client),
+            zpl_field(&label.oda),
+            format!("{} x {} cm", label.row.width, label.row.height),
+            zpl_field(
+                format!(
+                    "{} - {} {}",
+                    label.row.plise_name, label.row.color, label.row.renk_kodu
+                )
+                .trim_end(),
+            ),
+            format!("{} ({}/{})", label.code, label.row.idx, label.count),
+        ];
+        zpl.push_str("^XA\n^CI28\n");
+        zpl.push_str(&format!("^PW{}\n^LL{}\n", width, height));
+        for (i, line) in lines.iter().enumerate() {
+            zpl.push_str(&format!(
+                "^FO{},{}^A0N,{},{}^FB{},1,0,L^FH_^FD{}^FS\n",
+                margin,
+                margin + i as u32 * line_height,
+                font,
+                font,
+                text_width,
+                line
+            ));
+        }
+        zpl.push_str(&format!(
+            "^FO{},{}^BQN,2,{}^FDQA,{}^FS\n",
+            qr_x, margin, magnification, label.code
+        ));
+        zpl.push_str("^XZ\n");
+    }
+    zpl
+}
+
+/// Resolution the PDF labels are drawn at, about 300 dpi.
+const PDF_DOTS_PER_MM: u32 = 12;
+
+/// A grayscale drawing surface, white to start with.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![255; (width * height) as usize],
+        }
+    }
+
+    /// Darkens a pixel by `coverage`, 0 leaving it as it is and 1 making it black.
+    fn darken(&mut self, x: i32, y: i32, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let pixel = &mut self.pixels[(y as u32 * self.width + x as u32) as usize];
+        *pixel = (*pixel).min(((1. - coverage.clamp(0., 1.)) * 255.) as u8);
+    }
+
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.darken(x as i32, y as i32, 1.);
+            }
+        }
+    }
+
+    /// Draws one line of text with its top at `top`, cut short with `…` if it is wider
+    /// than `max_width`.
+    fn text(&mut self, font: &FontRef, size: f32, x: f32, top: f32, max_width: f32, text: &str) {
+        let font = font.as_scaled(PxScale::from(size));
+        let width = |text: &str| -> f32 {
+            let mut previous = None;
+            text.chars()
+                .map(|c| {
+                    let id = font.glyph_id(c);
+                    let kern = previous.map_or(0., |previous| font.kern(previous, id));
+                    previous = Some(id);
+                    kern + font.h_advance(id)
+                })
+                .sum()
+        };
+        let mut text = text.to_string();
+        if width(&text) > max_width {
+            while !text.is_empty() && width(&format!("{}…", text)) > max_width {
+                text.pop();
+            }
+            text.push('…');
+        }
+
+        let baseline = top + font.ascent();
+        let mut caret = x;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += font.kern(previous, id);
+            }
+            previous = Some(id);
+            let glyph = id.with_scale_and_position(font.scale(), point(caret, baseline));
+            caret += font.h_advance(id);
+            if let Some(outline) = font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    self.darken(
+                        bounds.min.x as i32 + gx as i32,
+                        bounds.min.y as i32 + gy as i32,
+                        coverage,
+                    );
+                });
+            }
+        }
+    }
+
+    fn qr(&mut self, code: &QrCode, x: u32, y: u32, module: u32) {
+        let width = code.width() as u32;
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            if color == Color::Dark {
+                let (column, row) = (i as u32 % width, i as u32 / width);
+                self.fill(x + column * module, y + row * module, module, module);
+            }
+        }
+    }
+}
+
+/// The same labels as the HTML ones, drawn into a PDF with one page per label, the
+/// page sized to the label.
+pub fn pdf(
+    labels: &[Label],
+    client: &str,
+    company: &str,
+    size: LabelSize,
+) -> Result<Vec<u8>, AppError> {
+    let fonts = egui::FontDefinitions::default();
+    let data = fonts
+        .font_data
+        .get("Ubuntu-Light")
+        .ok_or(AppError::Pdf("yazı tipi bulunamadı"))?;
+    let font = FontRef::try_from_slice_and_index(&data.font, data.index)
+        .map_err(|_| AppError::Pdf("yazı tipi okunamadı"))?;
+
+    let (width_mm, height_mm) = size.mm();
+    let (width, height) = (width_mm * PDF_DOTS_PER_MM, height_mm * PDF_DOTS_PER_MM);
+    let margin = 3 * PDF_DOTS_PER_MM;
+    let small = height_mm < 45;
+    let qr_width = if small { 20 } else { 28 } * PDF_DOTS_PER_MM;
+    let text_width = (width - 3 * margin - qr_width) as f32;
+    let line_height = (height - 2 * margin) as f32 / 6.;
+    let font_size = (line_height * 0.8).min(if small { 4. } else { 5. } * PDF_DOTS_PER_MM as f32);
+    let (client, company) = (sanitize_line(client), sanitize_line(company));
+
+    let mut pages = Vec::new();
+    for label in labels {
+        let mut canvas = Canvas::new(width, height);
+        let oda = sanitize_line(&label.oda);
+        let lines = [
+            company.clone(),
+            client.clone(),
+            if oda.is_empty() {
+                String::new()
+            } else {
+                format!("Oda: {}", oda)
+            },
+            format!("{} x {} cm", label.row.width, label.row.height),
+            format!(
+                "{} - {} {}",
+                label.row.plise_name, label.row.color, label.row.renk_kodu
+            )
+            .trim_end()
+            .to_string(),
+            format!("Sıra {} / {}", label.row.idx, label.count),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let size = if i == 0 { font_size * 0.8 } else { font_size };
+            canvas.text(
+                &font,
+                size,
+                margin as f32,
+                margin as f32 + i as f32 * line_height,
+                text_width,
+                line,
+            );
+        }
+
+        let code = QrCode::new(&label.code)?;
+        let module = qr_width / code.width() as u32;
+        let qr_x = width - margin - code.width() as u32 * module;
+        canvas.qr(&code, qr_x, margin, module);
+        canvas.text(
+            &font,
+            font_size * 0.8,
+            qr_x as f32,
+            (margin + code.width() as u32 * module) as f32 + font_size * 0.2,
+            (width - margin - qr_x) as f32,
+            &label.code,
+        );
+
+        pages.push(Page {
+            width_mm: width_mm as f32,
+            height_mm: height_mm as f32,
+            pixel_width: width,
+            pixel_height: height,
+            pixels: canvas.pixels,
+        });
+    }
+    Ok(pdf::write(&pages))
+}
+
+/// Sends ZPL straight to a printer: a device such as `/dev/usb/lp0`, or a shared printer
+/// such as `\\server\zebra` on Windows.
+pub fn send_to_printer(printer: &str, zpl: &str) -> Result<(), AppError> {
+    let path = PathBuf::from(printer);
+    OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .and_then(|mut device| device.write_all(zpl.as_bytes()))
+        .map_err(|source| AppError::Printer { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_labels() -> Vec<Label> {
+        let mut consumables = vec![Consumable::default(); 2];
+        consumables[0].oda = "Çocuk Odası".to_string();
+        consumables[1].renk_kodu = "RAL 7016".to_string();
+        labels(&consumables, 2, 12).unwrap()
+    }
+
+    #[test]
+    fn pdf_has_a_page_per_label_sized_to_it() {
+        let pdf = pdf(
+            &two_labels(),
+            "Işıl Öztürk",
+            "DOĞUŞ SİNEKLİK",
+            LabelSize::Kucuk,
+        )
+        .unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Type /Pages /Kids [3 0 R 6 0 R] /Count 2"));
+        assert_eq!(text.matches("/MediaBox [0 0 164.41 113.39]").count(), 2);
+        assert!(text.contains("/Width 696 /Height 480"));
+
+        // Every cross-reference entry points at its object.
+        let start = text.rfind("startxref\n").unwrap();
+        let xref: usize = text[start..].lines().nth(1).unwrap().parse().unwrap();
+        let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+        assert!(table.starts_with("xref\n0 9\n"));
+        let offsets: Vec<usize> = table
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 8);
+        for (i, offset) in offsets.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn text_is_cut_to_its_width() {
+        let fonts = egui::FontDefinitions::default();
+        let data = &fonts.font_data["Ubuntu-Light"];
+        let font = FontRef::try_from_slice_and_index(&data.font, data.index).unwrap();
+        let mut canvas = Canvas::new(200, 40);
+        canvas.text(&font, 30., 0., 0., 100., "İğneada Şişli Çarşı ıhlamur");
+        let dark_columns: Vec<u32> = (0..canvas.width)
+            .filter(|x| (0..canvas.height).any(|y| canvas.pixels[(y * 200 + x) as usize] < 128))
+            .collect();
+        assert!(!dark_columns.is_empty());
+        assert!(*dark_columns.last().unwrap() < 100);
+    }
+}
@@ -1,20 +1,53 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+mod bom;
+mod brand;
+mod chart;
 mod consumable;
+mod error;
+mod escape;
 mod html;
+mod label;
+mod output;
+mod pdf;
 mod plise;
 mod price;
+mod price_list;
+mod purchase;
+mod schedule;
+mod settings;
+mod spreadsheet;
+mod store;
+mod survey;
+mod what_if;
+mod work_order;
 
+use chrono::{Datelike, Duration, Local, NaiveDate};
 use eframe::egui;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 
 // use eframe::Theme;
 
-use consumable::Consumable;
+use bom::Bom;
+use brand::Brand;
+use consumable::{Consumable, ConsumableRow};
+use error::AppError;
 use html::Html;
-use plise::{ColorName, PliseName};
-use price::Price;
+use label::LabelSize;
+use output::{DocumentKind, OutputSettings};
+use plise::{ColorName, MountType, PliseName, TulType};
+use price::{kar_percent, series_margins, Accessory, LabourMode, Price, PriceProfile};
+use price_list::{PriceImport, PriceListSettings};
+use purchase::{PurchaseSettings, Requirement, Source};
+use schedule::{DayOrder, ScheduleSettings, DISPLAY_FORMAT};
+use settings::Versioned;
+use store::{Customer, ProductionLine, Quote, QuoteSummary, StockLevel, Store};
+use survey::{Survey, SurveySettings};
+use what_if::{Comparison, Delta, Order, WhatIf};
 
 fn main() -> Result<(), eframe::Error> {
+    settings::init_data_dir(std::env::args().skip(1));
     // env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1280.0, 720.0]),
@@ -29,6 +62,20 @@ struct Visibility {
     show_settings: bool,
     show_maliyet: bool,
     show_price: bool,
+    show_overwrite: bool,
+    show_brand: bool,
+    show_customers: bool,
+    show_price_import: bool,
+    show_profiles: bool,
+    show_target: bool,
+    show_what_if: bool,
+    show_breakdown: bool,
+    show_labour: bool,
+    show_schedule: bool,
+    show_purchase: bool,
+    show_catalogue: bool,
+    show_survey: bool,
+    show_accessories: bool,
 }
 
 impl Visibility {
@@ -37,6 +84,20 @@ impl Visibility {
             show_settings: false,
             show_maliyet: false,
             show_price: false,
+            show_overwrite: false,
+            show_brand: false,
+            show_customers: false,
+            show_price_import: false,
+            show_profiles: false,
+            show_target: false,
+            show_what_if: false,
+            show_breakdown: false,
+            show_labour: false,
+            show_schedule: false,
+            show_purchase: false,
+            show_catalogue: false,
+            show_survey: false,
+            show_accessories: false,
         }
     }
 }
@@ -47,17 +108,226 @@ struct MyApp {
     visibility: Visibility,
     consumables: Vec<Consumable>,
     price: Price,
+    output: OutputSettings,
+    brand: Brand,
+    price_list: PriceListSettings,
+    price_import: Option<PriceImport>,
+    pending_document: Option<PendingDocument>,
+    errors: Vec<AppError>,
+    /// `None` if the database could not be opened; the rest of the program still works.
+    store: Option<Store>,
+    customer: Customer,
+    /// Price profile of the current order, empty for the standard margins.
+    profile: String,
+    quote_notes: String,
+    target_price: f32,
+    target_includes_kdv: bool,
+    what_if: WhatIf,
+    what_if_current: bool,
+    /// Saved quotes picked for the what-if panel, with their lines.
+    what_if_quotes: Vec<(QuoteSummary, Vec<Consumable>)>,
+    search_query: String,
+    customers: Vec<Customer>,
+    quotes: Vec<QuoteSummary>,
+    schedule: ScheduleSettings,
+    /// Lines of the accepted quotes and the days they are planned for.
+    production: Vec<ProductionLine>,
+    /// Weeks the calendar is moved from the current one.
+    schedule_week: i64,
+    /// A quote being dragged to another day in the calendar, and the day it was on.
+    dragging: Option<(i64, NaiveDate)>,
+    /// The saved quote the current order came from or was saved as.
+    quote_id: Option<i64>,
+    /// Document number of the last work order printed for the current order, so the
+    /// labels carry the same line codes.
+    work_order_number: Option<u32>,
+    purchase: PurchaseSettings,
+    stock: Vec<StockLevel>,
+    /// Orders whose materials are bought: the current one and accepted quotes.
+    purchase_current: bool,
+    purchase_quotes: BTreeSet<i64>,
+    survey: SurveySettings,
+    /// The line being surveyed and the measurements entered so far.
+    survey_line: usize,
+    survey_draft: Survey,
+}
+
+const DAY_NAMES: [&str; 7] = ["Pzt", "Sal", "Çar", "Per", "Cum", "Cmt", "Paz"];
+
+/// Puts one settings file back to its defaults.
+type SettingsReset = fn(&mut MyApp) -> Result<(), AppError>;
+
+/// How to reset the settings file at `path`, if it is one of the app's settings in the
+/// data folder. Files such as an imported price list are not settings and cannot be
+/// reset.
+fn settings_reset(path: &Path) -> Option<SettingsReset> {
+    if path.parent() != Some(settings::data_dir()) {
+        return None;
+    }
+    let reset: SettingsReset = match path.file_name()?.to_str()? {
+        Price::FILE_NAME => |app| settings::reset(Price::FILE_NAME).map(|price| app.price = price),
+        OutputSettings::FILE_NAME => {
+            |app| settings::reset(OutputSettings::FILE_NAME).map(|output| app.output = output)
+        }
+        Brand::FILE_NAME => |app| settings::reset(Brand::FILE_NAME).map(|brand| app.brand = brand),
+        PriceListSettings::FILE_NAME => |app| {
+            settings::reset(PriceListSettings::FILE_NAME)
+                .map(|price_list| app.price_list = price_list)
+        },
+        ScheduleSettings::FILE_NAME => |app| {
+            settings::reset(ScheduleSettings::FILE_NAME).map(|schedule| app.schedule = schedule)
+        },
+        PurchaseSettings::FILE_NAME => |app| {
+            settings::reset(PurchaseSettings::FILE_NAME).map(|purchase| {
+                app.purchase = purchase;
+                app.purchase.sync_prices(&app.price);
+            })
+        },
+        SurveySettings::FILE_NAME => {
+            |app| settings::reset(SurveySettings::FILE_NAME).map(|survey| app.survey = survey)
+        }
+        _ => return None,
+    };
+    Some(reset)
+}
+
+/// A generated document waiting for the user to confirm overwriting an existing file,
+/// or to pick another folder after writing it failed.
+struct PendingDocument {
+    kind: DocumentKind,
+    /// The client, or the supplier of a purchase order, for the file name.
+    name: String,
+    extension: &'static str,
+    path: PathBuf,
+    content: Vec<u8>,
+    /// The document number printed on it.
+    number: u32,
+}
+
+/// Falls back to the defaults when a settings file cannot be loaded, keeping the
+/// error so the user can decide what to do with the file.
+fn load_or_default<T: Default>(result: Result<T, AppError>, errors: &mut Vec<AppError>) -> T {
+    result.unwrap_or_else(|e| {
+        errors.push(e);
+        T::default()
+    })
+}
+
+/// Picks one of the price profiles, or the standard margins (an empty name).
+fn profile_combo(ui: &mut egui::Ui, id: &str, price: &Price, profile: &mut String) {
+    let selected = if profile.is_empty() {
+        "Standart"
+    } else {
+        profile.as_str()
+    };
+    egui::ComboBox::from_id_source(id)
+        .selected_text(selected.to_string())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(profile, "".to_string(), "Standart");
+            for p in &price.profiles {
+                ui.selectable_value(profile, p.name.clone(), &p.name);
+            }
+        });
+}
+
+/// One day of the production calendar. Returns where it was drawn, and the quote whose
+/// chip started being dragged, if any.
+fn schedule_day(
+    ui: &mut egui::Ui,
+    schedule: &ScheduleSettings,
+    day: NaiveDate,
+    today: bool,
+    load: f32,
+    orders: &[DayOrder],
+) -> (egui::Rect, Option<i64>) {
+    let mut dragged = None;
+    let cell = egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_min_size(egui::vec2(100., 80.));
+        ui.vertical(|ui| {
+            let date = day.format("%d.%m").to_string();
+            if today {
+                ui.strong(date);
+            } else {
+                ui.label(date);
+            }
+            if !schedule.is_work_day(day) {
+                ui.weak("Tatil");
+            }
+            if load > 0. || schedule.is_work_day(day) {
+                let text = format!("{:.0} / {:.0} dk", load, schedule.daily_minutes);
+                if load > schedule.daily_minutes {
+                    ui.colored_label(egui::Color32::RED, text);
+                } else {
+                    ui.label(text);
+                }
+            }
+            for order in orders {
+                let chip = format!(
+                    "{} {} ({} ad.)",
+                    order.number, order.client_name, order.count
+                );
+                let response = ui
+                    .add(egui::Label::new(chip).sense(egui::Sense::drag()))
+                    .on_hover_text(format!("{:.0} dk", order.minutes))
+                    .on_hover_cursor(egui::CursorIcon::Grab);
+                if response.drag_started() {
+                    dragged = Some(order.quote_id);
+                }
+            }
+        });
+    });
+    (cell.response.rect, dragged)
 }
 
 impl Default for MyApp {
     fn default() -> Self {
-        Self {
+        let mut errors = Vec::new();
+        let price = load_or_default(Price::create_from_file(), &mut errors);
+        let store = Store::open()
+            .and_then(|store| store.import_prices_once(&price).map(|_| store))
+            .map_err(|e| errors.push(e))
+            .ok();
+        let mut app = Self {
             item_count: 1,
             client_name: "".to_string(),
             visibility: Visibility::new(),
             consumables: vec![Consumable::default(); 100],
-            price: Price::create_from_file(),
-        }
+            price,
+            output: load_or_default(OutputSettings::create_from_file(), &mut errors),
+            brand: load_or_default(Brand::create_from_file(), &mut errors),
+            price_list: load_or_default(PriceListSettings::create_from_file(), &mut errors),
+            schedule: load_or_default(ScheduleSettings::create_from_file(), &mut errors),
+            purchase: load_or_default(PurchaseSettings::create_from_file(), &mut errors),
+            survey: load_or_default(SurveySettings::create_from_file(), &mut errors),
+            price_import: None,
+            pending_document: None,
+            errors,
+            store,
+            customer: Customer::default(),
+            profile: "".to_string(),
+            quote_notes: "".to_string(),
+            target_price: 0.,
+            target_includes_kdv: true,
+            what_if: WhatIf::default(),
+            what_if_current: true,
+            what_if_quotes: Vec::new(),
+            search_query: "".to_string(),
+            customers: Vec::new(),
+            quotes: Vec::new(),
+            production: Vec::new(),
+            schedule_week: 0,
+            dragging: None,
+            quote_id: None,
+            work_order_number: None,
+            purchase_current: true,
+            purchase_quotes: BTreeSet::new(),
+            stock: Vec::new(),
+            survey_line: 0,
+            survey_draft: Survey::default(),
+        };
+        app.purchase.sync_prices(&app.price);
+        app.refresh_production();
+        app
     }
 }
 
@@ -70,9 +340,15 @@ impl eframe::App for MyApp {
             });
         });
         egui::CentralPanel::default().show(ctx, |ui| {
-            if ui.button("Ayarlar").clicked() {
-                self.visibility.show_settings = true;
-            }
+            ui.horizontal(|ui| {
+                if ui.button("Ayarlar").clicked() {
+                    self.visibility.show_settings = true;
+                }
+                if ui.button("Müşteriler ve Teklifler").clicked() {
+                    self.visibility.show_customers = true;
+                    self.refresh_search();
+                }
+            });
             ui.horizontal(|ui| {
                 ui.strong("Plise Adedi: ");
                 if ui.button("-").clicked() & (self.item_count > 1) {
@@ -97,9 +373,20 @@ impl eframe::App for MyApp {
                         ui.label("");
                         ui.strong("Boya Tipi");
                         ui.label("");
+                        ui.label("");
+                        ui.strong("Renk Kodu");
+                        ui.strong("Oda");
+                        ui.strong("Tül");
+                        ui.strong("Montaj");
+                        ui.label("");
+                        ui.strong("Aksesuar");
                         ui.end_row();
 
                         for i in 0..self.item_count {
+                            let mount_before = (
+                                self.consumables[i as usize].get_plise_name(),
+                                self.consumables[i as usize].montaj,
+                            );
                             ui.strong(format!("{}:", i + 1));
                             ui.add(
                                 egui::DragValue::new(&mut self.consumables[i as usize].width)
@@ -169,6 +456,99 @@ impl eframe::App for MyApp {
                             {
                                 self.consumables[i as usize].set_plise_color(ColorName::Ahsap);
                             }
+                            if ui
+                                .add(egui::RadioButton::new(
+                                    self.consumables[i as usize].check_plise_color(ColorName::Ozel),
+                                    "Özel RAL",
+                                ))
+                                .clicked()
+                            {
+                                self.consumables[i as usize].set_plise_color(ColorName::Ozel);
+                            }
+                            ui.add(
+                                egui::TextEdit::singleline(
+                                    &mut self.consumables[i as usize].renk_kodu,
+                                )
+                                .hint_text("RAL 7016")
+                                .desired_width(80.),
+                            );
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.consumables[i as usize].oda)
+                                    .desired_width(100.),
+                            );
+                            let consumable = &mut self.consumables[i as usize];
+                            // The mesh is pleated across the roll, so the pleat cannot be
+                            // taller than the roll is wide.
+                            let roll_width = self.price.tul.get(consumable.tul).roll_width;
+                            let too_tall = consumable.height > roll_width;
+                            let mut selected = egui::RichText::new(consumable.tul.label());
+                            if too_tall {
+                                selected = selected.color(ui.visuals().error_fg_color);
+                            }
+                            let combo = egui::ComboBox::from_id_source(("tul", i))
+                                .selected_text(selected)
+                                .show_ui(ui, |ui| {
+                                    for tul in TulType::ALL {
+                                        ui.selectable_value(&mut consumable.tul, tul, tul.label());
+                                    }
+                                });
+                            if too_tall {
+                                combo.response.on_hover_text(format!(
+                                    "Boy, {} cm rulo eninden uzun.",
+                                    roll_width
+                                ));
+                            }
+                            let montaj = &mut self.consumables[i as usize].montaj;
+                            egui::ComboBox::from_id_source(("montaj", i))
+                                .selected_text(montaj.label())
+                                .show_ui(ui, |ui| {
+                                    for mount in MountType::ALL {
+                                        ui.selectable_value(montaj, mount, mount.label());
+                                    }
+                                });
+                            // A surveyed line is sized from its opening, and the deduction
+                            // depends on the series and mount type.
+                            let consumable = &mut self.consumables[i as usize];
+                            if (consumable.get_plise_name(), consumable.montaj) != mount_before {
+                                if let Some(survey) = consumable.olcu {
+                                    self.survey.apply(consumable, survey);
+                                }
+                            }
+                            if ui.button("Ölçü").clicked() {
+                                self.survey_line = i as usize;
+                                self.survey_draft =
+                                    self.consumables[i as usize].olcu.unwrap_or_default();
+                                self.visibility.show_survey = true;
+                            }
+                            let accessories = &self.price.accessories;
+                            let consumable = &mut self.consumables[i as usize];
+                            let plise_name = consumable.get_plise_name();
+                            // Add-ons removed from the list or not offered for the series
+                            // chosen since are dropped, so the documents match the cost.
+                            consumable.aksesuarlar.retain(|name| {
+                                accessories
+                                    .iter()
+                                    .any(|a| &a.name == name && a.offered_for(plise_name))
+                            });
+                            let title = match consumable.aksesuarlar.len() {
+                                0 => "Yok".to_string(),
+                                count => format!("{} adet", count),
+                            };
+                            ui.menu_button(title, |ui| {
+                                for accessory in
+                                    accessories.iter().filter(|a| a.offered_for(plise_name))
+                                {
+                                    let mut chosen =
+                                        consumable.aksesuarlar.contains(&accessory.name);
+                                    if ui.checkbox(&mut chosen, &accessory.name).changed() {
+                                        if chosen {
+                                            consumable.aksesuarlar.push(accessory.name.clone());
+                                        } else {
+                                            consumable.aksesuarlar.retain(|n| n != &accessory.name);
+                                        }
+                                    }
+                                }
+                            });
                             ui.end_row();
                         }
                     });
@@ -183,6 +563,19 @@ impl eframe::App for MyApp {
                 if ui.button("Fiyat Göster").clicked() {
                     self.visibility.show_price = true;
                 }
+                if ui.button("Maliyet Duyarlılığı").clicked() {
+                    self.visibility.show_what_if = true;
+                    self.refresh_search();
+                }
+                if ui.button("Üretim Planı").clicked() {
+                    self.visibility.show_schedule = true;
+                    self.refresh_production();
+                }
+                if ui.button("Satın Alma").clicked() {
+                    self.visibility.show_purchase = true;
+                    self.refresh_production();
+                    self.refresh_stock();
+                }
             })
         });
 
@@ -197,16 +590,425 @@ impl eframe::App for MyApp {
         if self.visibility.show_settings {
             self.show_settings(ctx, _frame);
         }
+
+        if self.visibility.show_overwrite {
+            self.show_overwrite(ctx, _frame);
+        }
+
+        if self.visibility.show_brand {
+            self.show_brand(ctx, _frame);
+        }
+
+        if self.visibility.show_customers {
+            self.show_customers(ctx, _frame);
+        }
+
+        if self.visibility.show_price_import {
+            self.show_price_import(ctx, _frame);
+        }
+
+        if self.visibility.show_profiles {
+            self.show_profiles(ctx, _frame);
+        }
+
+        if self.visibility.show_target {
+            self.show_target(ctx, _frame);
+        }
+
+        if self.visibility.show_what_if {
+            self.show_what_if(ctx, _frame);
+        }
+
+        if self.visibility.show_breakdown {
+            self.show_breakdown(ctx, _frame);
+        }
+
+        if self.visibility.show_labour {
+            self.show_labour(ctx, _frame);
+        }
+
+        if self.visibility.show_schedule {
+            self.show_schedule(ctx, _frame);
+        }
+
+        if self.visibility.show_survey {
+            self.show_survey(ctx, _frame);
+        }
+
+        if self.visibility.show_accessories {
+            self.show_accessories(ctx, _frame);
+        }
+
+        if self.visibility.show_purchase {
+            self.show_purchase(ctx, _frame);
+        }
+
+        if self.visibility.show_catalogue {
+            self.show_catalogue(ctx, _frame);
+        }
+
+        if !self.errors.is_empty() {
+            self.show_error(ctx, _frame);
+        }
     }
 }
 
 impl MyApp {
+    /// Prices for the current order, with its price profile applied.
+    fn order_price(&self) -> Price {
+        self.price.with_profile(&self.profile)
+    }
+
+    fn rows(&self) -> Vec<ConsumableRow> {
+        self.consumables
+            .iter()
+            .take(self.item_count as usize)
+            .enumerate()
+            .map(|(i, c)| c.row(i + 1))
+            .collect()
+    }
+
+    /// Saves a generated document, asking first if a file with the same name exists.
+    /// HTML documents are opened in the browser once written.
+    fn write_document(
+        &mut self,
+        kind: DocumentKind,
+        extension: &'static str,
+        content: Result<Vec<u8>, AppError>,
+    ) {
+        self.write_named_document(kind, self.client_name.clone(), extension, content);
+    }
+
+    fn write_named_document(
+        &mut self,
+        kind: DocumentKind,
+        name: String,
+        extension: &'static str,
+        content: Result<Vec<u8>, AppError>,
+    ) {
+        let content = match content {
+            Ok(content) => content,
+            Err(e) => {
+                self.errors.push(e);
+                return;
+            }
+        };
+        let path = self.output.document_path(kind, &name, extension);
+        self.place_document(PendingDocument {
+            kind,
+            name,
+            extension,
+            path,
+            content,
+            number: self.output.next_document_number,
+        });
+    }
+
+    fn place_document(&mut self, document: PendingDocument) {
+        if document.path.exists() {
+            self.pending_document = Some(document);
+            self.visibility.show_overwrite = true;
+        } else {
+            self.save_document(document);
+        }
+    }
+
+    fn save_document(&mut self, document: PendingDocument) {
+        if let Err(e) = output::write_file(&document.path, &document.content) {
+            // Kept so the error window can retry in another folder.
+            self.pending_document = Some(document);
+            self.errors.push(e);
+            return;
+        }
+        // Labels carry the codes of the last work order actually saved.
+        if document.kind == DocumentKind::IsEmri {
+            self.work_order_number = Some(document.number);
+        }
+        self.output.next_document_number += 1;
+        if let Err(e) = self.output.to_file() {
+            self.errors.push(e);
+        }
+        if matches!(document.extension, "html" | "pdf") && !self.output.save_without_opening {
+            if let Err(e) = Html::open(&document.path) {
+                self.errors.push(e);
+            }
+        }
+    }
+
+    /// Sends the labels to the label printer, or saves them as a document if none is set.
+    fn print_zpl_labels(&mut self, number: u32) {
+        let zpl = label::labels(&self.consumables, self.item_count, number)
+            .map(|labels| label::zpl(&labels, &self.client_name, self.output.label_size));
+        if self.output.label_printer.trim().is_empty() {
+            self.write_document(DocumentKind::Etiket, "zpl", zpl.map(String::into_bytes));
+            return;
+        }
+        if let Err(e) =
+            zpl.and_then(|zpl| label::send_to_printer(self.output.label_printer.trim(), &zpl))
+        {
+            self.errors.push(e);
+        }
+    }
+
+    fn refresh_search(&mut self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        match store.search_customers(&self.search_query) {
+            Ok(customers) => self.customers = customers,
+            Err(e) => self.errors.push(e),
+        }
+        match store.search_quotes(&self.search_query) {
+            Ok(quotes) => self.quotes = quotes,
+            Err(e) => self.errors.push(e),
+        }
+    }
+
+    fn save_quote(&mut self, total_price: f32) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let quote = Quote {
+            number: self.output.next_document_number,
+            customer_id: self.customer.id,
+            client_name: &self.client_name,
+            notes: &self.quote_notes,
+            profile: &self.profile,
+            total: total_price,
+            lines: &self.consumables[..self.item_count as usize],
+        };
+        match store.save_quote(&quote) {
+            Ok(id) => self.quote_id = Some(id),
+            Err(e) => self.errors.push(e),
+        }
+        self.refresh_search();
+    }
+
+    /// Replaces the current order with the lines of a saved quote.
+    fn load_quote(&mut self, quote: &QuoteSummary) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        match store.quote_lines(quote.id) {
+            Ok(mut lines) => {
+                lines.truncate(100);
+                self.item_count = (lines.len() as u32).max(1);
+                lines.resize(100, Consumable::default());
+                self.consumables = lines;
+                self.client_name = quote.client_name.clone();
+                self.quote_notes = quote.notes.clone();
+                self.profile = quote.profile.clone();
+                self.quote_id = Some(quote.id);
+                self.work_order_number = None;
+            }
+            Err(e) => self.errors.push(e),
+        }
+    }
+
+    fn refresh_production(&mut self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        match store.production_lines() {
+            Ok(lines) => self.production = lines,
+            Err(e) => self.errors.push(e),
+        }
+    }
+
+    fn save_production(&mut self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        if let Err(e) = store.save_schedule(&self.production) {
+            self.errors.push(e);
+        }
+    }
+
+    /// What the orders chosen in the purchase window need.
+    fn purchase_requirements(&self) -> Vec<Requirement> {
+        let current: &[Consumable] = if self.purchase_current {
+            &self.consumables[..self.item_count as usize]
+        } else {
+            &[]
+        };
+        let quotes: Vec<Consumable> = self
+            .production
+            .iter()
+            .filter(|line| self.purchase_quotes.contains(&line.quote_id))
+            .map(|line| line.consumable.clone())
+            .collect();
+        purchase::requirements(&[current, &quotes], &self.price)
+    }
+
+    fn refresh_stock(&mut self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        match store.stock() {
+            Ok(stock) => self.stock = stock,
+            Err(e) => self.errors.push(e),
+        }
+    }
+
+    /// Plans the accepted orders again from today and stores the result.
+    fn plan_production(&mut self) {
+        self.schedule
+            .plan(&self.price, &mut self.production, Local::now().date_naive());
+        self.save_production();
+    }
+
+    /// The delivery date of the current order: as planned once its quote is accepted,
+    /// estimated against the workshop load before that.
+    fn delivery_date(&self) -> Option<NaiveDate> {
+        let planned = self
+            .quote_id
+            .and_then(|id| self.schedule.deliveries(&self.production).remove(&id));
+        if planned.is_some() {
+            return planned;
+        }
+        let minutes: Vec<f32> = self
+            .consumables
+            .iter()
+            .take(self.item_count as usize)
+            .map(|c| self.price.assembly_minutes(c))
+            .collect();
+        self.schedule.estimate(
+            &schedule::loads(&self.production),
+            &minutes,
+            Local::now().date_naive(),
+        )
+    }
+
+    /// Replaces the settings file named in a parse or version error with the defaults.
+    fn reset_settings(&mut self, path: &Path) {
+        if let Some(Err(e)) = settings_reset(path).map(|reset| reset(self)) {
+            self.errors.push(e);
+        }
+    }
+
+    fn show_error(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("error_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Hata")
+                .with_inner_size([500.0, 250.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                let mut dismiss = false;
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let error = &self.errors[0];
+                    ui.strong("Bir hata oluştu:");
+                    ui.label(error.to_string());
+                    ui.label("");
+
+                    match error {
+                        AppError::Parse { path, .. } | AppError::Version { path, .. }
+                            if settings_reset(path).is_some() =>
+                        {
+                            let path = path.clone();
+                            if ui.button("Varsayılanlara Dön").clicked() {
+                                dismiss = true;
+                                self.reset_settings(&path);
+                            }
+                        }
+                        AppError::Document { .. } => {
+                            ui.horizontal(|ui| {
+                                ui.label("Belge Klasörü:");
+                                ui.text_edit_singleline(&mut self.output.documents_dir);
+                            });
+                            if ui.button("Bu Klasöre Kaydet").clicked() {
+                                dismiss = true;
+                                if let Some(mut document) = self.pending_document.take() {
+                                    document.path = self.output.document_path(
+                                        document.kind,
+                                        &document.name,
+                                        document.extension,
+                                    );
+                                    self.place_document(document);
+                                }
+                            }
+                        }
+                        AppError::Browser { .. }
+                            if ui.button("Bundan Sonra Açmadan Kaydet").clicked() =>
+                        {
+                            dismiss = true;
+                            self.output.save_without_opening = true;
+                            if let Err(e) = self.output.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                        _ => {}
+                    }
+                    if ui.button("Kapat").clicked() {
+                        dismiss = true;
+                        if let AppError::Document { .. } = self.errors[0] {
+                            self.pending_document = None;
+                        }
+                    }
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    dismiss = true;
+                    if let AppError::Document { .. } = self.errors[0] {
+                        self.pending_document = None;
+                    }
+                }
+                if dismiss {
+                    self.errors.remove(0);
+                }
+            },
+        );
+    }
+
+    fn show_overwrite(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("overwrite_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Dosya Mevcut")
+                .with_inner_size([500.0, 200.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    if let Some(document) = &self.pending_document {
+                        ui.strong("Bu isimde bir belge zaten var:");
+                        ui.label(document.path.display().to_string());
+                    }
+                    ui.label("");
+                    ui.horizontal(|ui| {
+                        if ui.button("Üzerine Yaz").clicked() {
+                            if let Some(document) = self.pending_document.take() {
+                                self.save_document(document);
+                            }
+                            self.visibility.show_overwrite = false;
+                        }
+                        if ui.button("Vazgeç").clicked() {
+                            self.pending_document = None;
+                            self.visibility.show_overwrite = false;
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.pending_document = None;
+                    self.visibility.show_overwrite = false;
+                }
+            },
+        );
+    }
+
     fn show_maliyet(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.show_viewport_immediate(
             egui::ViewportId::from_hash_of("maliyet_viewport"),
             egui::ViewportBuilder::default()
                 .with_title("Maliyet")
-                .with_inner_size([500.0, 250.0]),
+                .with_inner_size([500.0, 380.0]),
             |ctx, class| {
                 assert!(
                     class == egui::ViewportClass::Immediate,
@@ -234,20 +1036,166 @@ impl MyApp {
                     });
                     ui.label("");
 
+                    ui.horizontal(|ui| {
+                        if ui.button("Maliyet Dağılımı").clicked() {
+                            self.visibility.show_breakdown = true;
+                        }
+                        if ui
+                            .checkbox(
+                                &mut self.output.include_cost_breakdown,
+                                "Dağılımı belgeye ekle",
+                            )
+                            .changed()
+                        {
+                            if let Err(e) = self.output.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                    });
                     if ui.button("Sarf Tablosunu Yazdır").clicked() {
-                        let mut html_content = String::new();
-                        self.consumables
-                            .iter()
-                            .take(self.item_count as usize)
-                            .enumerate()
-                            .for_each(|c| {
-                                html_content =
-                                    format!("{}{}", html_content, c.1.generate_html_table(c.0+1));
-                            });
-
-                        Html::create_consumables_html(&html_content, maliyet, &self.client_name);
+                        let breakdown = self.output.include_cost_breakdown.then(|| {
+                            self.price
+                                .order_breakdown(&self.consumables, self.item_count)
+                        });
+                        let content = Html::create_consumables_html(
+                            &self.rows(),
+                            maliyet,
+                            breakdown.as_ref(),
+                            &self.client_name,
+                            self.output.next_document_number,
+                            &self.brand,
+                        );
+                        self.write_document(
+                            DocumentKind::Maliyet,
+                            "html",
+                            content.map(String::into_bytes),
+                        );
                         self.visibility.show_maliyet = false;
                     }
+                    ui.horizontal(|ui| {
+                        if ui.button("İş Emri Yazdır").clicked() {
+                            let delivery_date = self
+                                .delivery_date()
+                                .map(|date| date.format(DISPLAY_FORMAT).to_string());
+                            let content = work_order::lines(
+                                &self.consumables,
+                                self.item_count,
+                                self.output.next_document_number,
+                                &self.price,
+                            )
+                            .and_then(|lines| {
+                                Html::create_work_order_html(
+                                    &lines,
+                                    self.output.work_order_per_pleat,
+                                    delivery_date.as_deref(),
+                                    &self.client_name,
+                                    self.output.next_document_number,
+                                    &self.brand,
+                                )
+                            });
+                            self.write_document(
+                                DocumentKind::IsEmri,
+                                "html",
+                                content.map(String::into_bytes),
+                            );
+                        }
+                        if ui
+                            .checkbox(
+                                &mut self.output.work_order_per_pleat,
+                                "Her plise ayrı sayfa",
+                            )
+                            .changed()
+                        {
+                            if let Err(e) = self.output.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let number = self
+                            .work_order_number
+                            .unwrap_or(self.output.next_document_number);
+                        if ui.button("Etiket Yazdır").clicked() {
+                            let content = label::labels(&self.consumables, self.item_count, number)
+                                .and_then(|labels| {
+                                    Html::create_label_html(
+                                        &labels,
+                                        self.output.label_size,
+                                        &self.client_name,
+                                        number,
+                                        &self.brand,
+                                    )
+                                });
+                            self.write_document(
+                                DocumentKind::Etiket,
+                                "html",
+                                content.map(String::into_bytes),
+                            );
+                        }
+                        if ui.button("Etiket Yazdır (PDF)").clicked() {
+                            let content = label::labels(&self.consumables, self.item_count, number)
+                                .and_then(|labels| {
+                                    label::pdf(
+                                        &labels,
+                                        &self.client_name,
+                                        &self.brand.company_name,
+                                        self.output.label_size,
+                                    )
+                                });
+                            self.write_document(DocumentKind::Etiket, "pdf", content);
+                        }
+                        if ui.button("Etiket Yazdır (ZPL)").clicked() {
+                            self.print_zpl_labels(number);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Excel (XLSX) Aktar").clicked() {
+                            let content = spreadsheet::create_xlsx(
+                                &self.order_price(),
+                                &self.consumables,
+                                self.item_count,
+                                &self.client_name,
+                            );
+                            self.write_document(DocumentKind::Tablo, "xlsx", content);
+                        }
+                        if ui.button("ODS Aktar").clicked() {
+                            let content = spreadsheet::create_ods(
+                                &self.order_price(),
+                                &self.consumables,
+                                self.item_count,
+                                &self.client_name,
+                            );
+                            self.write_document(DocumentKind::Tablo, "ods", content);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Malzeme Listesi (JSON)").clicked() {
+                            let bom = Bom::new(
+                                &self.consumables,
+                                self.item_count,
+                                &self.client_name,
+                                &self.price,
+                            );
+                            self.write_document(
+                                DocumentKind::Malzeme,
+                                "json",
+                                bom.to_json().map(String::into_bytes),
+                            );
+                        }
+                        if ui.button("Malzeme Listesi (CSV)").clicked() {
+                            let bom = Bom::new(
+                                &self.consumables,
+                                self.item_count,
+                                &self.client_name,
+                                &self.price,
+                            );
+                            self.write_document(
+                                DocumentKind::Malzeme,
+                                "csv",
+                                Ok(bom.to_csv().into_bytes()),
+                            );
+                        }
+                    });
                     ui.label("");
                     if ui.button("Kapat").clicked() {
                         self.visibility.show_maliyet = false;
@@ -266,7 +1214,7 @@ impl MyApp {
             egui::ViewportId::from_hash_of("price_viewport"),
             egui::ViewportBuilder::default()
                 .with_title("Fiyat")
-                .with_inner_size([500.0, 250.0]),
+                .with_inner_size([500.0, 280.0]),
             |ctx, class| {
                 assert!(
                     class == egui::ViewportClass::Immediate,
@@ -275,9 +1223,14 @@ impl MyApp {
 
                 egui::CentralPanel::default().show(ctx, |ui| {
                     let (_, total_price, total_price_kdv) = self
-                        .price
+                        .order_price()
                         .calculate_prices(&self.consumables, self.item_count);
 
+                    ui.horizontal(|ui| {
+                        ui.strong("Fiyat Profili:");
+                        profile_combo(ui, "price_profile", &self.price, &mut self.profile);
+                    });
+
                     ui.horizontal(|ui| {
                         ui.strong("Kdv:");
                         ui.add(
@@ -292,26 +1245,48 @@ impl MyApp {
                         "Fiyat (Kdv Dahil - %{:.0}): {:.2}",
                         self.price.kdv, total_price_kdv
                     ));
+                    let delivery_date = self
+                        .delivery_date()
+                        .map(|date| date.format(DISPLAY_FORMAT).to_string());
+                    ui.label(format!(
+                        "Tahmini Teslim: {}",
+                        delivery_date.as_deref().unwrap_or("-")
+                    ));
                     ui.label("");
                     ui.horizontal(|ui| {
                         ui.strong("Müşteri Adını Giriniz:");
                         ui.text_edit_singleline(&mut self.client_name);
                     });
+                    ui.horizontal(|ui| {
+                        ui.strong("Teklif Notu:");
+                        ui.text_edit_singleline(&mut self.quote_notes);
+                    });
                     ui.label("");
-                    if ui.button("Fiyat Yazdır").clicked() {
-                        let mut html_content = String::new();
-                        self.consumables
-                            .iter()
-                            .take(self.item_count as usize)
-                            .enumerate()
-                            .for_each(|c| {
-                                html_content =
-                                    format!("{}{}", html_content, c.1.generate_wh_html_table(c.0+1));
-                            });
-
-                        Html::create_price_html(&html_content, total_price, self.price.kdv, &self.client_name);
-                        self.visibility.show_price = false;
-                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Fiyat Yazdır").clicked() {
+                            let content = Html::create_price_html(
+                                &self.rows(),
+                                total_price,
+                                self.price.kdv,
+                                delivery_date.as_deref(),
+                                &self.client_name,
+                                self.output.next_document_number,
+                                &self.brand,
+                            );
+                            self.write_document(
+                                DocumentKind::Fiyat,
+                                "html",
+                                content.map(String::into_bytes),
+                            );
+                            self.visibility.show_price = false;
+                        }
+                        if self.store.is_some() && ui.button("Teklifi Kaydet").clicked() {
+                            self.save_quote(total_price);
+                        }
+                        if ui.button("Hedef Fiyat Hesapla").clicked() {
+                            self.visibility.show_target = true;
+                        }
+                    });
                     ui.label("");
                     if ui.button("Kapat").clicked() {
                         self.visibility.show_price = false;
@@ -330,7 +1305,7 @@ impl MyApp {
             egui::ViewportId::from_hash_of("settings_viewport"),
             egui::ViewportBuilder::default()
                 .with_title("Fiyatlar Listesi")
-                .with_inner_size([500.0, 250.0]),
+                .with_inner_size([500.0, 600.0]),
             |ctx, class| {
                 assert!(
                     class == egui::ViewportClass::Immediate,
@@ -390,19 +1365,14 @@ impl MyApp {
                         ui.end_row();
                         ui.end_row();
 
-                        ui.strong("Tul");
                         ui.strong("Şerit");
                         ui.strong("Teker");
                         ui.strong("Klips");
                         ui.strong("Stop");
                         ui.strong("Dönüş");
+                        ui.strong("Adaptör");
                         ui.end_row();
 
-                        ui.add(
-                            egui::DragValue::new(&mut self.price.tul_price)
-                                .clamp_range(0..=1000)
-                                .speed(0.2),
-                        );
                         ui.add(
                             egui::DragValue::new(&mut self.price.serit_price)
                                 .clamp_range(0..=100)
@@ -429,6 +1399,48 @@ impl MyApp {
                                 .clamp_range(0..=100)
                                 .speed(0.1),
                         );
+                        ui.add(
+                            egui::DragValue::new(&mut self.price.adaptor_price)
+                                .clamp_range(0..=10000),
+                        );
+                    });
+
+                    ui.label("");
+                    egui::Grid::new("tul_grid").min_col_width(70.).show(ui, |ui| {
+                        ui.strong("Tül Tipi");
+                        ui.strong("Fiyat (TL/m²)");
+                        ui.strong("Rulo Eni");
+                        ui.end_row();
+                        for tul in TulType::ALL {
+                            let tul_price = self.price.tul.get_mut(tul);
+                            ui.label(tul.label());
+                            ui.add(
+                                egui::DragValue::new(&mut tul_price.price)
+                                    .clamp_range(0..=1000)
+                                    .speed(0.2),
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut tul_price.roll_width)
+                                    .clamp_range(0..=1000)
+                                    .suffix(" cm"),
+                            );
+                            ui.end_row();
+                        }
+                    });
+
+                    ui.label("");
+                    ui.horizontal(|ui| {
+                        ui.strong("Özel RAL (TL/m):");
+                        ui.add(
+                            egui::DragValue::new(&mut self.price.color_price.ozel)
+                                .clamp_range(0..=10000),
+                        );
+                        ui.label("");
+                        ui.strong("Boya Kurulumu (TL/renk):");
+                        ui.add(
+                            egui::DragValue::new(&mut self.price.boya_kurulum)
+                                .clamp_range(0..=100000),
+                        );
                     });
 
                     ui.label("");
@@ -453,16 +1465,134 @@ impl MyApp {
                                 .clamp_range(0..=100)
                                 .speed(0.1),
                         );
+                        ui.label("");
+                        ui.strong("Asgari Kar:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.price.min_kar)
+                                .clamp_range(-100..=100)
+                                .speed(0.1),
+                        );
                     });
                     ui.label("");
                     ui.horizontal(|ui| {
                         if ui.button("Fiyatları Güncelle").clicked() {
-                            self.price.to_file();
+                            if let Err(e) = self.price.to_file() {
+                                self.errors.push(e);
+                            }
+                            if let Some(store) = &self.store {
+                                if let Err(e) = store.save_price_list("Ayarlar", &self.price) {
+                                    self.errors.push(e);
+                                }
+                            }
+                        }
+                        if ui.button("Fiyat Profilleri").clicked() {
+                            self.visibility.show_profiles = true;
+                        }
+                        if ui.button("İşçilik Modeli").clicked() {
+                            self.visibility.show_labour = true;
+                        }
+                        if ui.button("Aksesuarlar").clicked() {
+                            self.visibility.show_accessories = true;
                         }
                         if ui.button("Kapat").clicked() {
                             self.visibility.show_settings = false;
                         }
                     });
+
+                    ui.separator();
+                    ui.strong("Fiyat Listesi Paylaşımı");
+                    egui::Grid::new("price_list_grid").show(ui, |ui| {
+                        ui.label("Yayınlayan:");
+                        ui.text_edit_singleline(&mut self.price_list.publisher);
+                        ui.end_row();
+                        ui.label("Paylaşım Anahtarı:");
+                        ui.add(egui::TextEdit::singleline(&mut self.price_list.key).password(true))
+                            .on_hover_text("Bütün şubelerde aynı olmalı");
+                        ui.end_row();
+                        ui.label("Liste Dosyası:");
+                        ui.text_edit_singleline(&mut self.price_list.file);
+                        ui.end_row();
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Fiyat Listesini Yayınla").clicked() {
+                            if let Err(e) = self
+                                .price_list
+                                .to_file()
+                                .and_then(|_| self.price_list.export(&self.price))
+                            {
+                                self.errors.push(e);
+                            }
+                        }
+                        if ui.button("Fiyat Listesini İçe Aktar").clicked() {
+                            if let Err(e) = self.price_list.to_file() {
+                                self.errors.push(e);
+                            }
+                            match self.price_list.import() {
+                                Ok(list) => {
+                                    let changes = list.changes(&self.price);
+                                    self.price_import = Some(PriceImport { list, changes });
+                                    self.visibility.show_price_import = true;
+                                }
+                                Err(e) => self.errors.push(e),
+                            }
+                        }
+                    });
+
+                    ui.separator();
+                    ui.strong("Belge Ayarları");
+                    ui.label(format!("Veri Klasörü: {}", settings::data_dir().display()));
+                    egui::Grid::new("output_grid").show(ui, |ui| {
+                        ui.label("Belge Klasörü:");
+                        ui.text_edit_singleline(&mut self.output.documents_dir);
+                        ui.end_row();
+                        ui.label("Dosya Adı Şablonu:");
+                        ui.text_edit_singleline(&mut self.output.file_name_template)
+                            .on_hover_text("{tur}, {no}, {musteri}, {tarih}");
+                        ui.end_row();
+                        ui.label("Sıradaki Belge No:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.output.next_document_number)
+                                .clamp_range(1..=999999),
+                        );
+                        ui.end_row();
+                        ui.label("Etiket Boyutu:");
+                        egui::ComboBox::from_id_source("label_size")
+                            .selected_text(self.output.label_size.label())
+                            .show_ui(ui, |ui| {
+                                for size in LabelSize::ALL {
+                                    ui.selectable_value(
+                                        &mut self.output.label_size,
+                                        size,
+                                        size.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                        ui.label("Etiket Yazıcısı (ZPL):");
+                        ui.text_edit_singleline(&mut self.output.label_printer)
+                            .on_hover_text(
+                                "Örn. /dev/usb/lp0 veya \\\\sunucu\\zebra. Boş bırakılırsa .zpl dosyası kaydedilir.",
+                            );
+                        ui.end_row();
+                    });
+                    ui.checkbox(
+                        &mut self.output.per_client_folder,
+                        "Her müşteri için ayrı klasör",
+                    );
+                    ui.checkbox(
+                        &mut self.output.save_without_opening,
+                        "Belgeleri kaydettikten sonra tarayıcıda açma",
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("Belge Ayarlarını Kaydet").clicked() {
+                            if let Err(e) = self.output.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                        if ui.button("Firma Bilgileri").clicked() {
+                            self.visibility.show_brand = true;
+                        }
+                    });
                 });
 
                 if ctx.input(|i| i.viewport().close_requested()) {
@@ -472,4 +1602,1668 @@ impl MyApp {
             },
         );
     }
+
+    fn show_brand(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("brand_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Firma Bilgileri")
+                .with_inner_size([500.0, 400.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        egui::Grid::new("brand_grid").show(ui, |ui| {
+                            ui.label("Firma Adı:");
+                            ui.text_edit_singleline(&mut self.brand.company_name);
+                            ui.end_row();
+                            ui.label("Adres:");
+                            ui.text_edit_multiline(&mut self.brand.address);
+                            ui.end_row();
+                            ui.label("Telefon:");
+                            ui.text_edit_singleline(&mut self.brand.phone);
+                            ui.end_row();
+                            ui.label("E-posta:");
+                            ui.text_edit_singleline(&mut self.brand.email);
+                            ui.end_row();
+                            ui.label("Logo Dosyası:");
+                            ui.text_edit_singleline(&mut self.brand.logo_path);
+                            ui.end_row();
+                            ui.label("Alt Bilgi / Şartlar:");
+                            ui.text_edit_multiline(&mut self.brand.footer_terms);
+                            ui.end_row();
+                            ui.label("Banka Bilgileri:");
+                            ui.text_edit_multiline(&mut self.brand.bank_details);
+                            ui.end_row();
+                            ui.label("Başlık Rengi:");
+                            ui.text_edit_singleline(&mut self.brand.header_color);
+                            ui.end_row();
+                            ui.label("Çizgi Rengi:");
+                            ui.text_edit_singleline(&mut self.brand.border_color);
+                            ui.end_row();
+                            ui.label("Yazı Rengi:");
+                            ui.text_edit_singleline(&mut self.brand.text_color);
+                            ui.end_row();
+                        });
+                        ui.label("");
+                        ui.horizontal(|ui| {
+                            if ui.button("Kaydet").clicked() {
+                                if let Err(e) = self.brand.to_file() {
+                                    self.errors.push(e);
+                                }
+                            }
+                            if ui.button("Kapat").clicked() {
+                                self.visibility.show_brand = false;
+                            }
+                        });
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_brand = false;
+                }
+            },
+        );
+    }
+
+    fn show_customers(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("customers_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Müşteriler ve Teklifler")
+                .with_inner_size([600.0, 500.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    if self.store.is_none() {
+                        ui.label(
+                            "Veritabanı açılamadığı için müşteri ve teklif kaydı yapılamıyor.",
+                        );
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_customers = false;
+                        }
+                        return;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.strong("Ara:");
+                        if ui.text_edit_singleline(&mut self.search_query).changed() {
+                            self.refresh_search();
+                        }
+                    });
+                    ui.separator();
+                    ui.strong("Müşteri");
+                    egui::Grid::new("customer_grid").show(ui, |ui| {
+                        ui.label("Adı:");
+                        ui.text_edit_singleline(&mut self.customer.name);
+                        ui.end_row();
+                        ui.label("Telefon:");
+                        ui.text_edit_singleline(&mut self.customer.phone);
+                        ui.end_row();
+                        ui.label("Adres:");
+                        ui.text_edit_multiline(&mut self.customer.address);
+                        ui.end_row();
+                        ui.label("Notlar:");
+                        ui.text_edit_multiline(&mut self.customer.notes);
+                        ui.end_row();
+                        ui.label("Fiyat Profili:");
+                        profile_combo(
+                            ui,
+                            "customer_profile",
+                            &self.price,
+                            &mut self.customer.profile,
+                        );
+                        ui.end_row();
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Müşteriyi Kaydet").clicked() {
+                            if let Some(store) = &self.store {
+                                match store.save_customer(&self.customer) {
+                                    Ok(id) => {
+                                        self.customer.id = Some(id);
+                                        self.client_name = self.customer.name.clone();
+                                        self.profile = self.customer.profile.clone();
+                                    }
+                                    Err(e) => self.errors.push(e),
+                                }
+                            }
+                            self.refresh_search();
+                        }
+                        if ui.button("Yeni Müşteri").clicked() {
+                            self.customer = Customer::default();
+                        }
+                    });
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.strong("Müşteriler");
+                        let mut selected = None;
+                        egui::Grid::new("customers_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for customer in &self.customers {
+                                    ui.label(&customer.name);
+                                    ui.label(&customer.phone);
+                                    if ui.button("Seç").clicked() {
+                                        selected = Some(customer.clone());
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        if let Some(customer) = selected {
+                            self.client_name = customer.name.clone();
+                            self.profile = customer.profile.clone();
+                            self.customer = customer;
+                        }
+
+                        ui.label("");
+                        ui.strong("Teklifler");
+                        let mut loaded = None;
+                        let mut accepted = None;
+                        egui::Grid::new("quotes_grid").striped(true).show(ui, |ui| {
+                            for (i, quote) in self.quotes.iter().enumerate() {
+                                ui.label(format!("{}", quote.number));
+                                ui.label(quote.created_at.get(..10).unwrap_or(&quote.created_at));
+                                ui.label(&quote.client_name);
+                                ui.label(format!("{:.2}", quote.total));
+                                ui.label(&quote.notes);
+                                ui.label(if quote.profile.is_empty() {
+                                    "Standart"
+                                } else {
+                                    &quote.profile
+                                });
+                                if ui.button("Yükle").clicked() {
+                                    loaded = Some(i);
+                                }
+                                if quote.accepted {
+                                    ui.label("Onaylandı");
+                                } else if ui.button("Onayla").clicked() {
+                                    accepted = Some(quote.id);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(i) = loaded {
+                            let quote = self.quotes.swap_remove(i);
+                            self.load_quote(&quote);
+                            self.refresh_search();
+                        }
+                        if let Some(id) = accepted {
+                            if let Some(store) = &self.store {
+                                if let Err(e) = store.accept_quote(id) {
+                                    self.errors.push(e);
+                                }
+                            }
+                            self.refresh_production();
+                            self.plan_production();
+                            self.refresh_search();
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_customers = false;
+                }
+            },
+        );
+    }
+
+    fn show_price_import(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("price_import_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Fiyat Listesi Karşılaştırma")
+                .with_inner_size([800.0, 400.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                let Some(import) = &mut self.price_import else {
+                    self.visibility.show_price_import = false;
+                    return;
+                };
+                let mut close = false;
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.label(format!(
+                        "Yayınlayan: {}    Tarih: {}",
+                        import.list.publisher,
+                        import
+                            .list
+                            .published_at
+                            .get(..10)
+                            .unwrap_or(&import.list.published_at)
+                    ));
+                    ui.separator();
+                    if import.changes.is_empty() {
+                        ui.label("Listedeki fiyatlar mevcut fiyatlarla aynı.");
+                    } else {
+                        egui::ScrollArea::vertical()
+                            .max_height(250.)
+                            .show(ui, |ui| {
+                                egui::Grid::new("price_import_grid")
+                                    .striped(true)
+                                    .show(ui, |ui| {
+                                        ui.label("");
+                                        ui.strong("Bileşen");
+                                        ui.strong("Mevcut");
+                                        ui.strong("Yeni");
+                                        ui.strong("Değişim");
+                                        ui.end_row();
+                                        for change in &mut import.changes {
+                                            ui.checkbox(&mut change.accept, "");
+                                            ui.label(&change.label);
+                                            ui.label(&change.old);
+                                            ui.label(&change.new);
+                                            match change.percent() {
+                                                Some(percent) => {
+                                                    ui.label(format!("%{:+.1}", percent))
+                                                }
+                                                None => ui.label(""),
+                                            };
+                                            ui.end_row();
+                                        }
+                                    });
+                            });
+                        ui.horizontal(|ui| {
+                            if ui.button("Tümünü Seç").clicked() {
+                                import.changes.iter_mut().for_each(|c| c.accept = true);
+                            }
+                            if ui.button("Hiçbirini Seçme").clicked() {
+                                import.changes.iter_mut().for_each(|c| c.accept = false);
+                            }
+                        });
+                    }
+                    ui.label("");
+                    ui.horizontal(|ui| {
+                        if ui.button("Seçilenleri Uygula").clicked() {
+                            price_list::apply(&mut self.price, &import.changes);
+                            if let Err(e) = self.price.to_file() {
+                                self.errors.push(e);
+                            }
+                            if let Some(store) = &self.store {
+                                let name = format!(
+                                    "{} ({})",
+                                    import.list.publisher, import.list.published_at
+                                );
+                                if let Err(e) = store.save_price_list(&name, &self.price) {
+                                    self.errors.push(e);
+                                }
+                            }
+                            close = true;
+                        }
+                        if ui.button("Vazgeç").clicked() {
+                            close = true;
+                        }
+                    });
+                });
+                if close || ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.price_import = None;
+                    self.visibility.show_price_import = false;
+                }
+            },
+        );
+    }
+
+    fn show_profiles(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("profiles_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Fiyat Profilleri")
+                .with_inner_size([600.0, 450.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let components = self.price.components();
+                    let mut removed = None;
+                    egui::ScrollArea::vertical()
+                        .max_height(330.)
+                        .show(ui, |ui| {
+                            for (i, profile) in self.price.profiles.iter_mut().enumerate() {
+                                ui.push_id(i, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.text_edit_singleline(&mut profile.name);
+                                        if ui.button("Sil").clicked() {
+                                            removed = Some(i);
+                                        }
+                                    });
+                                    ui.horizontal(|ui| {
+                                        for (label, kar) in [
+                                            ("Klasik Kar:", &mut profile.klasik_kar),
+                                            ("Geniş Kar:", &mut profile.genis_kar),
+                                            ("İnce Kar:", &mut profile.ince_kar),
+                                        ] {
+                                            ui.label(label);
+                                            ui.add(
+                                                egui::DragValue::new(kar)
+                                                    .clamp_range(-100..=500)
+                                                    .speed(0.1),
+                                            );
+                                        }
+                                    });
+                                    ui.collapsing("Özel Bileşen Fiyatları", |ui| {
+                                        egui::Grid::new("overrides_grid").show(ui, |ui| {
+                                            for component in &components {
+                                                let mut enabled =
+                                                    profile.overrides.contains_key(component.key);
+                                                if ui
+                                                    .checkbox(&mut enabled, component.label)
+                                                    .changed()
+                                                {
+                                                    if enabled {
+                                                        profile.overrides.insert(
+                                                            component.key.to_string(),
+                                                            component.value,
+                                                        );
+                                                    } else {
+                                                        profile.overrides.remove(component.key);
+                                                    }
+                                                }
+                                                match profile.overrides.get_mut(component.key) {
+                                                    Some(value) => {
+                                                        ui.add(
+                                                            egui::DragValue::new(value).speed(0.1),
+                                                        );
+                                                    }
+                                                    None => {
+                                                        ui.label(format!("{:.2}", component.value));
+                                                    }
+                                                }
+                                                ui.label(component.unit);
+                                                ui.end_row();
+                                            }
+                                        });
+                                    });
+                                    ui.separator();
+                                });
+                            }
+                        });
+                    if let Some(i) = removed {
+                        self.price.profiles.remove(i);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Profil Ekle").clicked() {
+                            self.price.profiles.push(PriceProfile::default());
+                        }
+                        if ui.button("Kaydet").clicked() {
+                            if let Err(e) = self.price.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_profiles = false;
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_profiles = false;
+                }
+            },
+        );
+    }
+
+    fn show_target(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("target_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Hedef Fiyat")
+                .with_inner_size([550.0, 450.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.strong("Hedef Fiyat:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.target_price)
+                                .clamp_range(0..=10_000_000)
+                                .speed(1.),
+                        );
+                        ui.checkbox(&mut self.target_includes_kdv, "Kdv Dahil");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.strong("Asgari Kar (%):");
+                        ui.add(
+                            egui::DragValue::new(&mut self.price.min_kar)
+                                .clamp_range(-100..=100)
+                                .speed(0.1),
+                        );
+                    });
+                    ui.label("");
+
+                    let price = self.order_price();
+                    let kdv = 1. + price.kdv / 100.;
+                    let target = if self.target_includes_kdv {
+                        self.target_price / kdv
+                    } else {
+                        self.target_price
+                    };
+                    let (maliyet, total_price, _) =
+                        price.calculate_prices(&self.consumables, self.item_count);
+                    let lines = price.target_margins(&self.consumables, self.item_count, target);
+                    let min_kar = self.price.min_kar;
+                    let kar_label = |ui: &mut egui::Ui, kar: f32| {
+                        let text = format!("%{:.1}", kar);
+                        if kar < min_kar {
+                            ui.colored_label(egui::Color32::RED, text);
+                        } else {
+                            ui.label(text);
+                        }
+                    };
+
+                    egui::Grid::new("target_totals_grid").show(ui, |ui| {
+                        ui.label("Başabaş Fiyatı:");
+                        ui.label(format!("{:.2} (Kdv Dahil {:.2})", maliyet, maliyet * kdv));
+                        ui.end_row();
+                        ui.label("Liste Fiyatı:");
+                        ui.label(format!(
+                            "{:.2} (Kdv Dahil {:.2})",
+                            total_price,
+                            total_price * kdv
+                        ));
+                        ui.end_row();
+                        ui.label("Hedef (Kdv Hariç):");
+                        ui.label(format!("{:.2}", target));
+                        ui.end_row();
+                        ui.label("Ortalama Kar:");
+                        kar_label(ui, kar_percent(maliyet, target));
+                        ui.end_row();
+                    });
+                    ui.separator();
+
+                    egui::ScrollArea::vertical()
+                        .max_height(220.)
+                        .show(ui, |ui| {
+                            ui.strong("Serilere Göre");
+                            egui::Grid::new("target_series_grid")
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.strong("Seri");
+                                    ui.strong("Adet");
+                                    ui.strong("Maliyet");
+                                    ui.strong("Hedef Payı");
+                                    ui.strong("Kar");
+                                    ui.end_row();
+                                    for series in series_margins(&lines) {
+                                        ui.label(series.plise_name.label());
+                                        ui.label(format!("{}", series.count));
+                                        ui.label(format!("{:.2}", series.maliyet));
+                                        ui.label(format!("{:.2}", series.price));
+                                        kar_label(ui, kar_percent(series.maliyet, series.price));
+                                        ui.end_row();
+                                    }
+                                });
+                            ui.label("");
+
+                            let below: Vec<_> = lines
+                                .iter()
+                                .filter(|line| kar_percent(line.maliyet, line.price) < min_kar)
+                                .collect();
+                            if below.is_empty() {
+                                ui.label("Bütün satırlar asgari karın üzerinde.");
+                            } else {
+                                ui.strong("Asgari Karın Altındaki Satırlar");
+                                egui::Grid::new("target_lines_grid")
+                                    .striped(true)
+                                    .show(ui, |ui| {
+                                        ui.strong("Satır");
+                                        ui.strong("Seri");
+                                        ui.strong("Maliyet");
+                                        ui.strong("Hedef Payı");
+                                        ui.strong("Kar");
+                                        ui.end_row();
+                                        for line in below {
+                                            ui.label(format!("{}", line.line));
+                                            ui.label(line.plise_name.label());
+                                            ui.label(format!("{:.2}", line.maliyet));
+                                            ui.label(format!("{:.2}", line.price));
+                                            kar_label(ui, kar_percent(line.maliyet, line.price));
+                                            ui.end_row();
+                                        }
+                                    });
+                            }
+                        });
+                    ui.label("");
+                    if ui.button("Kapat").clicked() {
+                        self.visibility.show_target = false;
+                    }
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_target = false;
+                }
+            },
+        );
+    }
+
+    fn show_what_if(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("what_if_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Maliyet Duyarlılığı")
+                .with_inner_size([650.0, 550.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.strong("Fiyat Değişimleri (%)");
+                    ui.horizontal(|ui| {
+                        for (label, change) in [
+                            ("Profil:", &mut self.what_if.profil),
+                            ("Tül:", &mut self.what_if.tul),
+                            ("Donanım:", &mut self.what_if.hardware),
+                            ("İşçilik:", &mut self.what_if.isci),
+                        ] {
+                            ui.label(label);
+                            ui.add(
+                                egui::DragValue::new(change)
+                                    .clamp_range(-100..=500)
+                                    .speed(0.1),
+                            );
+                        }
+                    });
+                    ui.label("Kayıtlı fiyatlar değişmez.");
+                    ui.separator();
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.checkbox(&mut self.what_if_current, "Mevcut Sipariş");
+                        if self.store.is_some() {
+                            ui.collapsing("Kayıtlı Teklifler", |ui| {
+                                if ui.text_edit_singleline(&mut self.search_query).changed() {
+                                    self.refresh_search();
+                                }
+                                let mut toggled = None;
+                                for quote in &self.quotes {
+                                    let mut selected =
+                                        self.what_if_quotes.iter().any(|(q, _)| q.id == quote.id);
+                                    let text = format!(
+                                        "{} - {} - {:.2}",
+                                        quote.number, quote.client_name, quote.total
+                                    );
+                                    if ui.checkbox(&mut selected, text).changed() {
+                                        toggled = Some((quote.clone(), selected));
+                                    }
+                                }
+                                match toggled {
+                                    Some((quote, true)) => {
+                                        if let Some(store) = &self.store {
+                                            match store.quote_lines(quote.id) {
+                                                Ok(lines) => {
+                                                    self.what_if_quotes.push((quote, lines))
+                                                }
+                                                Err(e) => self.errors.push(e),
+                                            }
+                                        }
+                                    }
+                                    Some((quote, false)) => {
+                                        self.what_if_quotes.retain(|(q, _)| q.id != quote.id)
+                                    }
+                                    None => {}
+                                }
+                            });
+                        }
+
+                        let mut orders = Vec::new();
+                        if self.what_if_current {
+                            orders.push(Order {
+                                name: "Mevcut Sipariş".to_string(),
+                                lines: &self.consumables[..self.item_count as usize],
+                                profile: &self.profile,
+                            });
+                        }
+                        for (quote, lines) in &self.what_if_quotes {
+                            orders.push(Order {
+                                name: format!("{} - {}", quote.number, quote.client_name),
+                                lines,
+                                profile: &quote.profile,
+                            });
+                        }
+                        let comparison = Comparison::new(&self.price, &self.what_if, &orders);
+
+                        let delta_cells = |ui: &mut egui::Ui, delta: &Delta| {
+                            ui.label(format!("{:.2}", delta.old));
+                            ui.label(format!("{:.2}", delta.new));
+                            ui.label(format!("{:+.2}", delta.difference()));
+                            ui.label(format!("%{:+.1}", delta.percent()));
+                        };
+                        let header = |ui: &mut egui::Ui, first: &str| {
+                            ui.strong(first);
+                            ui.strong("Şimdi");
+                            ui.strong("Sonra");
+                            ui.strong("Fark");
+                            ui.strong("Değişim");
+                            ui.end_row();
+                        };
+
+                        ui.separator();
+                        egui::Grid::new("what_if_totals_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                header(ui, "Toplam");
+                                ui.label("Maliyet");
+                                delta_cells(ui, &comparison.maliyet);
+                                ui.end_row();
+                                ui.label("Fiyat (Kdv Hariç)");
+                                delta_cells(ui, &comparison.price);
+                                ui.end_row();
+                            });
+                        ui.label("");
+                        egui::Grid::new("what_if_components_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                header(ui, "Bileşen");
+                                for (label, delta) in &comparison.components {
+                                    ui.label(*label);
+                                    delta_cells(ui, delta);
+                                    ui.end_row();
+                                }
+                            });
+                        ui.label("");
+                        egui::Grid::new("what_if_series_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                header(ui, "Seri");
+                                for series in &comparison.series {
+                                    ui.label(format!("{} Maliyet", series.plise_name.label()));
+                                    delta_cells(ui, &series.maliyet);
+                                    ui.end_row();
+                                    ui.label(format!("{} Fiyat", series.plise_name.label()));
+                                    delta_cells(ui, &series.price);
+                                    ui.end_row();
+                                }
+                            });
+                        ui.label("");
+                        egui::Grid::new("what_if_orders_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                header(ui, "Sipariş Fiyatı");
+                                for (name, delta) in &comparison.orders {
+                                    ui.label(name);
+                                    delta_cells(ui, delta);
+                                    ui.end_row();
+                                }
+                            });
+                        ui.label("");
+                        ui.horizontal(|ui| {
+                            if ui.button("Sıfırla").clicked() {
+                                self.what_if = WhatIf::default();
+                            }
+                            if ui.button("Kapat").clicked() {
+                                self.visibility.show_what_if = false;
+                            }
+                        });
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_what_if = false;
+                }
+            },
+        );
+    }
+
+    fn show_breakdown(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("breakdown_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Maliyet Dağılımı")
+                .with_inner_size([700.0, 550.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let breakdown = self
+                        .price
+                        .order_breakdown(&self.consumables, self.item_count);
+                    egui::ScrollArea::both().max_height(450.).show(ui, |ui| {
+                        egui::Grid::new("breakdown_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Sıra");
+                                for label in &breakdown.labels {
+                                    ui.strong(*label);
+                                }
+                                ui.strong("Toplam");
+                                ui.end_row();
+                                for (i, values) in breakdown.lines.iter().enumerate() {
+                                    ui.label(format!("{}", i + 1));
+                                    for value in values {
+                                        ui.label(format!("{:.2}", value));
+                                    }
+                                    ui.label(format!("{:.2}", values.iter().sum::<f32>()));
+                                    ui.end_row();
+                                }
+                                ui.strong("Toplam");
+                                for value in &breakdown.totals {
+                                    ui.strong(format!("{:.2}", value));
+                                }
+                                ui.strong(format!("{:.2}", breakdown.total));
+                                ui.end_row();
+                                ui.label("");
+                                for value in &breakdown.totals {
+                                    let share = if breakdown.total > 0. {
+                                        value / breakdown.total * 100.
+                                    } else {
+                                        0.
+                                    };
+                                    ui.label(format!("%{:.1}", share));
+                                }
+                                ui.end_row();
+                            });
+                        ui.separator();
+                        chart::legend(ui, &breakdown.labels);
+                        ui.horizontal(|ui| {
+                            let bars: Vec<_> = breakdown
+                                .lines
+                                .iter()
+                                .enumerate()
+                                .map(|(i, values)| (format!("{}", i + 1), values.clone()))
+                                .collect();
+                            chart::stacked_bars(ui, &bars, 150.);
+                            ui.add_space(16.);
+                            chart::pie(ui, &breakdown.totals, 75.);
+                        });
+                    });
+                    if ui.button("Kapat").clicked() {
+                        self.visibility.show_breakdown = false;
+                    }
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_breakdown = false;
+                }
+            },
+        );
+    }
+
+    fn show_labour(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("labour_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("İşçilik Modeli")
+                .with_inner_size([550.0, 400.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let labour = &mut self.price.labour;
+                    ui.horizontal(|ui| {
+                        ui.strong("Hesaplama:");
+                        for mode in LabourMode::ALL {
+                            ui.radio_value(&mut labour.mode, mode, mode.label());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.strong("İşçi Maliyeti (%):");
+                        ui.add(
+                            egui::DragValue::new(&mut self.price.isci_maliyeti)
+                                .clamp_range(0..=100)
+                                .speed(0.1),
+                        );
+                    });
+                    let labour = &mut self.price.labour;
+                    ui.horizontal(|ui| {
+                        ui.strong("Saat Ücreti (TL):");
+                        ui.add(
+                            egui::DragValue::new(&mut labour.hourly_rate)
+                                .clamp_range(0..=100_000)
+                                .speed(1.),
+                        );
+                        ui.strong("Genel Gider (TL/saat):");
+                        ui.add(
+                            egui::DragValue::new(&mut labour.overhead_rate)
+                                .clamp_range(0..=100_000)
+                                .speed(1.),
+                        );
+                    });
+                    ui.label("");
+                    egui::Grid::new("labour_grid").striped(true).show(ui, |ui| {
+                        ui.strong("Seri");
+                        ui.strong("Sabit (dk)");
+                        ui.strong("Profil Metresi (dk)");
+                        ui.strong("Parça Başına (dk)");
+                        ui.end_row();
+                        for (name, time) in [
+                            ("Klasik", &mut labour.klasik),
+                            ("Geniş", &mut labour.genis),
+                            ("İnce", &mut labour.ince),
+                        ] {
+                            ui.label(name);
+                            for minutes in [
+                                &mut time.fixed_minutes,
+                                &mut time.minutes_per_metre,
+                                &mut time.minutes_per_piece,
+                            ] {
+                                ui.add(
+                                    egui::DragValue::new(minutes)
+                                        .clamp_range(0..=1000)
+                                        .speed(0.1),
+                                );
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    ui.label("");
+                    let minutes: f32 = self
+                        .consumables
+                        .iter()
+                        .take(self.item_count as usize)
+                        .map(|consumable| self.price.assembly_minutes(consumable))
+                        .sum();
+                    ui.label(format!(
+                        "Mevcut siparişin montaj süresi: {:.0} dk ({:.1} saat)",
+                        minutes,
+                        minutes / 60.
+                    ));
+                    ui.label("");
+                    ui.horizontal(|ui| {
+                        if ui.button("Kaydet").clicked() {
+                            if let Err(e) = self.price.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_labour = false;
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_labour = false;
+                }
+            },
+        );
+    }
+
+    fn show_schedule(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("schedule_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Üretim Planı")
+                .with_inner_size([900.0, 650.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    if self.store.is_none() {
+                        ui.label("Veritabanı açılamadığı için üretim planı yapılamıyor.");
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_schedule = false;
+                        }
+                        return;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.strong("Günlük Kapasite:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.schedule.daily_minutes)
+                                .clamp_range(30..=2000)
+                                .suffix(" dk"),
+                        );
+                        ui.strong("Teslim Süresi:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.schedule.delivery_days)
+                                .clamp_range(0..=30)
+                                .suffix(" iş günü"),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.strong("Çalışma Günleri:");
+                        for (work_day, name) in self.schedule.work_days.iter_mut().zip(DAY_NAMES) {
+                            ui.checkbox(work_day, name);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Kaydet").clicked() {
+                            if let Err(e) = self.schedule.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                        if ui.button("Yeniden Planla").clicked() {
+                            self.plan_production();
+                        }
+                        ui.separator();
+                        if ui.button("<").clicked() {
+                            self.schedule_week -= 1;
+                        }
+                        if ui.button("Bu Hafta").clicked() {
+                            self.schedule_week = 0;
+                        }
+                        if ui.button(">").clicked() {
+                            self.schedule_week += 1;
+                        }
+                    });
+                    ui.label("Siparişleri sürükleyerek başka bir güne taşıyabilirsiniz.");
+                    ui.separator();
+
+                    let today = Local::now().date_naive();
+                    let start = today
+                        - Duration::days(today.weekday().num_days_from_monday() as i64)
+                        + Duration::weeks(self.schedule_week);
+                    let loads = schedule::loads(&self.production);
+                    let day_orders = schedule::day_orders(&self.production);
+                    let mut moved = None;
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        let mut cells = Vec::new();
+                        egui::Grid::new("schedule_grid").show(ui, |ui| {
+                            for name in DAY_NAMES {
+                                ui.strong(name);
+                            }
+                            ui.end_row();
+                            for week in 0..4 {
+                                for weekday in 0..7 {
+                                    let day = start + Duration::days(week * 7 + weekday);
+                                    let load = loads.get(&day).copied().unwrap_or(0.);
+                                    let orders = day_orders.get(&day).map(Vec::as_slice);
+                                    let (rect, dragged) = schedule_day(
+                                        ui,
+                                        &self.schedule,
+                                        day,
+                                        day == today,
+                                        load,
+                                        orders.unwrap_or_default(),
+                                    );
+                                    if let Some(quote_id) = dragged {
+                                        self.dragging = Some((quote_id, day));
+                                    }
+                                    cells.push((day, rect));
+                                }
+                                ui.end_row();
+                            }
+                        });
+
+                        if let Some((quote_id, from)) = self.dragging {
+                            ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+                            let target = ui
+                                .input(|i| i.pointer.hover_pos())
+                                .and_then(|pos| cells.iter().find(|(_, rect)| rect.contains(pos)));
+                            if let Some((_, rect)) = target {
+                                ui.painter().rect_stroke(
+                                    *rect,
+                                    2.,
+                                    egui::Stroke::new(2., ui.visuals().selection.bg_fill),
+                                );
+                            }
+                            if ui.input(|i| i.pointer.any_released()) {
+                                self.dragging = None;
+                                if let Some((to, _)) = target.filter(|(to, _)| *to != from) {
+                                    moved = Some((quote_id, from, *to));
+                                }
+                            }
+                        }
+
+                        ui.label("");
+                        ui.strong("Teslim Tarihleri");
+                        let deliveries = self.schedule.deliveries(&self.production);
+                        let mut shown = BTreeSet::new();
+                        egui::Grid::new("deliveries_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for line in &self.production {
+                                    let Some(date) = deliveries.get(&line.quote_id) else {
+                                        continue;
+                                    };
+                                    if shown.insert(line.quote_id) {
+                                        ui.label(format!("{}", line.number));
+                                        ui.label(&line.client_name);
+                                        ui.label(date.format(DISPLAY_FORMAT).to_string());
+                                        ui.end_row();
+                                    }
+                                }
+                            });
+                    });
+                    if let Some((quote_id, from, to)) = moved {
+                        schedule::move_quote(&mut self.production, quote_id, from, to);
+                        self.save_production();
+                    }
+                    ui.label("");
+                    if ui.button("Kapat").clicked() {
+                        self.visibility.show_schedule = false;
+                    }
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_schedule = false;
+                }
+            },
+        );
+    }
+
+    fn show_purchase(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("purchase_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Satın Alma")
+                .with_inner_size([700.0, 600.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.strong("Siparişler");
+                        ui.checkbox(&mut self.purchase_current, "Mevcut Sipariş");
+                        let mut accepted = BTreeSet::new();
+                        for line in &self.production {
+                            if !accepted.insert(line.quote_id) {
+                                continue;
+                            }
+                            let mut selected = self.purchase_quotes.contains(&line.quote_id);
+                            let text = format!("{} - {}", line.number, line.client_name);
+                            if ui.checkbox(&mut selected, text).changed() {
+                                if selected {
+                                    self.purchase_quotes.insert(line.quote_id);
+                                } else {
+                                    self.purchase_quotes.remove(&line.quote_id);
+                                }
+                            }
+                        }
+                        if accepted.is_empty() {
+                            ui.label("Onaylanmış teklif yok.");
+                        } else if ui.button("Tüm Onaylı Teklifler").clicked() {
+                            self.purchase_quotes = accepted;
+                        }
+
+                        let requirements = self.purchase_requirements();
+
+                        ui.separator();
+                        ui.strong("İhtiyaç ve Stok");
+                        let mut changed_stock = None;
+                        egui::Grid::new("requirements_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Malzeme");
+                                ui.strong("İhtiyaç");
+                                ui.strong("Stok");
+                                ui.end_row();
+                                for requirement in &requirements {
+                                    ui.label(requirement.name());
+                                    ui.label(format!(
+                                        "{:.2} {}",
+                                        requirement.quantity, requirement.unit
+                                    ));
+                                    let mut quantity = requirement
+                                        .stock(&self.stock)
+                                        .map_or(0., |level| level.quantity);
+                                    if ui
+                                        .add(
+                                            egui::DragValue::new(&mut quantity)
+                                                .clamp_range(0..=100000)
+                                                .suffix(format!(" {}", requirement.unit)),
+                                        )
+                                        .changed()
+                                    {
+                                        changed_stock = Some(requirement.stock_level(quantity));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        if let Some(level) = changed_stock {
+                            if let Some(store) = &self.store {
+                                if let Err(e) = store.set_stock(&level) {
+                                    self.errors.push(e);
+                                }
+                            }
+                            self.stock.retain(|l| {
+                                (&l.item, &l.series, &l.color)
+                                    != (&level.item, &level.series, &level.color)
+                            });
+                            self.stock.push(level);
+                        }
+
+                        ui.separator();
+                        ui.strong("Satın Alma Siparişleri");
+                        self.purchase.sync_prices(&self.price);
+                        let orders =
+                            purchase::purchase_orders(&self.purchase, &requirements, &self.stock);
+                        if orders.is_empty() {
+                            ui.label("Sipariş verilecek malzeme yok.");
+                        }
+                        let mut print = None;
+                        for (i, order) in orders.iter().enumerate() {
+                            ui.label("");
+                            ui.horizontal(|ui| {
+                                ui.strong(if order.supplier.is_empty() {
+                                    "Tedarikçi belirtilmemiş"
+                                } else {
+                                    &order.supplier
+                                });
+                                if order.lead_days > 0 {
+                                    ui.label(format!("Teslim: {} gün", order.lead_days));
+                                }
+                                if ui.button("Sipariş Belgesi Yazdır").clicked() {
+                                    print = Some(i);
+                                }
+                            });
+                            egui::Grid::new(("purchase_order_grid", i))
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.strong("Malzeme");
+                                    ui.strong("Parça No");
+                                    ui.strong("Eksik");
+                                    ui.strong("Paket");
+                                    ui.strong("Sipariş");
+                                    ui.strong("Birim Fiyat");
+                                    ui.strong("Tutar");
+                                    ui.end_row();
+                                    for line in &order.lines {
+                                        ui.label(&line.name);
+                                        ui.label(&line.part_no);
+                                        ui.label(format!("{:.2} {}", line.needed, line.unit));
+                                        ui.label(format!(
+                                            "{} x {} {}",
+                                            line.packs, line.pack_size, line.unit
+                                        ));
+                                        ui.label(format!("{} {}", line.quantity, line.unit));
+                                        ui.label(format!("{:.2}", line.unit_price));
+                                        ui.label(format!("{:.2}", line.total));
+                                        ui.end_row();
+                                    }
+                                });
+                            ui.label(format!("Toplam: {:.2} TL", order.total));
+                        }
+                        if let Some(order) = print.map(|i| &orders[i]) {
+                            let content = Html::create_purchase_order_html(
+                                order,
+                                self.output.next_document_number,
+                                &self.brand,
+                            );
+                            self.write_named_document(
+                                DocumentKind::SatinAlma,
+                                order.supplier.clone(),
+                                "html",
+                                content.map(String::into_bytes),
+                            );
+                        }
+
+                        ui.separator();
+                        if ui.button("Tedarik Kataloğu").clicked() {
+                            self.visibility.show_catalogue = true;
+                        }
+                        ui.label("");
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_purchase = false;
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_purchase = false;
+                }
+            },
+        );
+    }
+
+    fn show_catalogue(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("catalogue_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Tedarik Kataloğu")
+                .with_inner_size([800.0, 600.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    // The preferred sources always show the prices costing uses, even when
+                    // they were changed in the price list or by an import.
+                    self.purchase.sync_prices(&self.price);
+                    ui.label("Maliyet hesabında ve satın almada tercih edilen kaynak kullanılır.");
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for component in self.price.components() {
+                            let Some(part) = self
+                                .purchase
+                                .parts
+                                .iter_mut()
+                                .find(|part| part.component == component.key)
+                            else {
+                                continue;
+                            };
+                            let title =
+                                format!("{} ({} kaynak)", component.label, part.sources.len());
+                            ui.collapsing(title, |ui| {
+                                let count = part.sources.len();
+                                let mut removed = None;
+                                egui::Grid::new(("catalogue_grid", component.key)).show(
+                                    ui,
+                                    |ui| {
+                                        ui.strong("Tercih");
+                                        ui.strong("Tedarikçi");
+                                        ui.strong("Parça No");
+                                        ui.strong(format!("Fiyat ({})", component.unit));
+                                        ui.strong("Boy / Paket");
+                                        ui.strong("Asgari");
+                                        ui.strong("Teslim");
+                                        ui.end_row();
+                                        for (i, source) in part.sources.iter_mut().enumerate() {
+                                            ui.radio_value(&mut part.preferred, i, "");
+                                            ui.add(
+                                                egui::TextEdit::singleline(&mut source.supplier)
+                                                    .desired_width(140.),
+                                            );
+                                            ui.add(
+                                                egui::TextEdit::singleline(&mut source.part_no)
+                                                    .desired_width(80.),
+                                            );
+                                            ui.add(
+                                                egui::DragValue::new(&mut source.price)
+                                                    .clamp_range(0..=100000)
+                                                    .speed(0.1),
+                                            );
+                                            ui.add(
+                                                egui::DragValue::new(&mut source.pack_size)
+                                                    .clamp_range(0..=10000)
+                                                    .speed(0.1),
+                                            );
+                                            ui.add(
+                                                egui::DragValue::new(&mut source.min_quantity)
+                                                    .clamp_range(0..=100000),
+                                            );
+                                            ui.add(
+                                                egui::DragValue::new(&mut source.lead_days)
+                                                    .clamp_range(0..=365)
+                                                    .suffix(" gün"),
+                                            );
+                                            if count > 1 && ui.button("Sil").clicked() {
+                                                removed = Some(i);
+                                            }
+                                            ui.end_row();
+                                        }
+                                    },
+                                );
+                                if let Some(i) = removed {
+                                    part.sources.remove(i);
+                                    if part.preferred > i {
+                                        part.preferred -= 1;
+                                    }
+                                    part.preferred = part.preferred.min(part.sources.len() - 1);
+                                }
+                                if ui.button("Kaynak Ekle").clicked() {
+                                    part.sources.push(Source {
+                                        price: component.value,
+                                        ..Source::default()
+                                    });
+                                }
+                            });
+                        }
+                        self.purchase.apply_prices(&mut self.price);
+
+                        ui.separator();
+                        ui.strong("Kaynak Karşılaştırması");
+                        let requirements = self.purchase_requirements();
+                        let comparisons =
+                            purchase::compare(&self.purchase, &self.price, &requirements);
+                        if comparisons.is_empty() {
+                            ui.label("Birden fazla kaynağı olan malzeme yok.");
+                        }
+                        for (i, comparison) in comparisons.iter().enumerate() {
+                            ui.label("");
+                            ui.strong(comparison.label);
+                            if comparison.needed > 0. {
+                                ui.label(format!(
+                                    "Satın alma penceresinde seçili siparişlerin ihtiyacı: {:.2} {}",
+                                    comparison.needed, comparison.unit
+                                ));
+                            }
+                            egui::Grid::new(("comparison_grid", i))
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.strong("Tedarikçi");
+                                    ui.strong("Birim Fiyat");
+                                    ui.strong("Fark");
+                                    ui.strong("Asgari");
+                                    ui.strong("Teslim");
+                                    ui.strong("Sipariş Tutarı");
+                                    ui.strong("");
+                                    ui.end_row();
+                                    for source in &comparison.sources {
+                                        ui.label(&source.supplier);
+                                        ui.label(format!("{:.2}", source.price));
+                                        ui.label(format!("%{:+.1}", source.difference));
+                                        ui.label(format!(
+                                            "{} {}",
+                                            source.min_quantity, comparison.unit
+                                        ));
+                                        ui.label(format!("{} gün", source.lead_days));
+                                        ui.label(
+                                            source
+                                                .order_total
+                                                .map_or("-".to_string(), |t| format!("{:.2}", t)),
+                                        );
+                                        ui.label(match (source.preferred, source.cheapest) {
+                                            (true, true) => "Tercih, en uygun",
+                                            (true, false) => "Tercih",
+                                            (false, true) => "En uygun",
+                                            (false, false) => "",
+                                        });
+                                        ui.end_row();
+                                    }
+                                });
+                        }
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Kaydet").clicked() {
+                            if let Err(e) = self.purchase.to_file() {
+                                self.errors.push(e);
+                            }
+                            if let Err(e) = self.price.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                        if ui.button("Karşılaştırma Raporu Yazdır").clicked() {
+                            let requirements = self.purchase_requirements();
+                            let comparisons =
+                                purchase::compare(&self.purchase, &self.price, &requirements);
+                            let content = Html::create_source_comparison_html(
+                                &comparisons,
+                                self.output.next_document_number,
+                                &self.brand,
+                            );
+                            self.write_named_document(
+                                DocumentKind::Tedarik,
+                                "tedarikciler".to_string(),
+                                "html",
+                                content.map(String::into_bytes),
+                            );
+                        }
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_catalogue = false;
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_catalogue = false;
+                }
+            },
+        );
+    }
+
+    fn show_survey(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("survey_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title(format!("Ölçü Alma - Plise {}", self.survey_line + 1))
+                .with_inner_size([560.0, 480.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.label("Pervazın kaba ölçüsünü üç noktadan girin, en küçüğü kullanılır.");
+                    let consumable = &mut self.consumables[self.survey_line];
+                    egui::Grid::new("survey_grid").show(ui, |ui| {
+                        ui.label("");
+                        for name in ["Üst", "Orta", "Alt"] {
+                            ui.strong(name);
+                        }
+                        ui.end_row();
+                        ui.strong("En");
+                        for width in &mut self.survey_draft.widths {
+                            ui.add(
+                                egui::DragValue::new(width)
+                                    .clamp_range(0..=500)
+                                    .speed(0.1)
+                                    .suffix(" cm"),
+                            );
+                        }
+                        ui.end_row();
+                        ui.label("");
+                        for name in ["Sol", "Orta", "Sağ"] {
+                            ui.strong(name);
+                        }
+                        ui.end_row();
+                        ui.strong("Boy");
+                        for height in &mut self.survey_draft.heights {
+                            ui.add(
+                                egui::DragValue::new(height)
+                                    .clamp_range(0..=500)
+                                    .speed(0.1)
+                                    .suffix(" cm"),
+                            );
+                        }
+                        ui.end_row();
+                        ui.strong("Montaj");
+                        egui::ComboBox::from_id_source("survey_montaj")
+                            .selected_text(consumable.montaj.label())
+                            .show_ui(ui, |ui| {
+                                for mount in MountType::ALL {
+                                    ui.selectable_value(
+                                        &mut consumable.montaj,
+                                        mount,
+                                        mount.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                    });
+
+                    ui.separator();
+                    let finished = self.survey.finished_size(consumable, &self.survey_draft);
+                    match (self.survey_draft.opening(), finished) {
+                        (Some((width, height)), Some((finished_width, finished_height))) => {
+                            let (width_deduction, height_deduction) = self
+                                .survey
+                                .deduction(consumable.get_plise_name(), consumable.montaj);
+                            ui.label(format!("Kaba ölçü: {} x {} cm", width, height));
+                            ui.label(format!(
+                                "Pay ({}, {}): {} / {} cm",
+                                consumable.get_plise_name().label(),
+                                consumable.montaj.label(),
+                                width_deduction,
+                                height_deduction
+                            ));
+                            ui.strong(format!(
+                                "Plise ölçüsü: {} x {} cm",
+                                finished_width, finished_height
+                            ));
+                        }
+                        _ => {
+                            ui.label("En ve boy için en az birer ölçü girin.");
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(finished.is_some(), egui::Button::new("Uygula"))
+                            .clicked()
+                        {
+                            self.survey.apply(consumable, self.survey_draft);
+                            self.visibility.show_survey = false;
+                        }
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_survey = false;
+                        }
+                    });
+
+                    ui.separator();
+                    ui.collapsing("Ölçü Payları", |ui| {
+                        ui.label("Kaba ölçüden düşülen pay; eksi değer pliseyi büyütür.");
+                        egui::Grid::new("deduction_grid").show(ui, |ui| {
+                            ui.strong("Plise");
+                            ui.strong("Montaj");
+                            ui.strong("En");
+                            ui.strong("Boy");
+                            ui.end_row();
+                            for deduction in &mut self.survey.deductions {
+                                ui.label(deduction.plise_name.label());
+                                ui.label(deduction.mount.label());
+                                ui.add(
+                                    egui::DragValue::new(&mut deduction.width)
+                                        .clamp_range(-50..=50)
+                                        .speed(0.1)
+                                        .suffix(" cm"),
+                                );
+                                ui.add(
+                                    egui::DragValue::new(&mut deduction.height)
+                                        .clamp_range(-50..=50)
+                                        .speed(0.1)
+                                        .suffix(" cm"),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                        if ui.button("Kaydet").clicked() {
+                            if let Err(e) = self.survey.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_survey = false;
+                }
+            },
+        );
+    }
+
+    fn show_accessories(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("accessories_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("Aksesuarlar")
+                .with_inner_size([1000.0, 400.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.label("Metre ile satılan aksesuarların miktarı plise boyunun katıdır.");
+                    let mut removed = None;
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        egui::Grid::new("accessories_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Ad");
+                                for name in PliseName::ALL {
+                                    ui.strong(name.label());
+                                }
+                                ui.strong("Fiyat (TL)");
+                                ui.strong("Miktar");
+                                ui.strong("Metre");
+                                ui.strong("Tedarikçi");
+                                ui.strong("Parça No");
+                                ui.strong("Paket");
+                                ui.end_row();
+                                for (i, accessory) in self.price.accessories.iter_mut().enumerate()
+                                {
+                                    let old_name = accessory.name.clone();
+                                    if ui
+                                        .add(
+                                            egui::TextEdit::singleline(&mut accessory.name)
+                                                .desired_width(140.),
+                                        )
+                                        .changed()
+                                    {
+                                        // Lines refer to add-ons by name.
+                                        for consumable in &mut self.consumables {
+                                            for name in &mut consumable.aksesuarlar {
+                                                if *name == old_name {
+                                                    *name = accessory.name.clone();
+                                                }
+                                            }
+                                        }
+                                    }
+                                    for name in PliseName::ALL {
+                                        let mut offered = accessory.offered_for(name);
+                                        if ui.checkbox(&mut offered, "").changed() {
+                                            if offered {
+                                                accessory.series.push(name);
+                                            } else {
+                                                accessory.series.retain(|s| *s != name);
+                                            }
+                                        }
+                                    }
+                                    ui.add(
+                                        egui::DragValue::new(&mut accessory.price)
+                                            .clamp_range(0..=10000)
+                                            .speed(0.1),
+                                    );
+                                    ui.add(
+                                        egui::DragValue::new(&mut accessory.quantity)
+                                            .clamp_range(0..=100)
+                                            .speed(0.1),
+                                    );
+                                    ui.checkbox(&mut accessory.per_metre, "");
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut accessory.supplier)
+                                            .desired_width(140.),
+                                    );
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut accessory.part_no)
+                                            .desired_width(80.),
+                                    );
+                                    ui.add(
+                                        egui::DragValue::new(&mut accessory.pack_size)
+                                            .clamp_range(0..=10000)
+                                            .speed(0.1),
+                                    );
+                                    if ui.button("Sil").clicked() {
+                                        removed = Some(i);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                    if let Some(i) = removed {
+                        self.price.accessories.remove(i);
+                    }
+                    ui.label("");
+                    ui.horizontal(|ui| {
+                        if ui.button("Aksesuar Ekle").clicked() {
+                            self.price.accessories.push(Accessory::default());
+                        }
+                        if ui.button("Kaydet").clicked() {
+                            if let Err(e) = self.price.to_file() {
+                                self.errors.push(e);
+                            }
+                        }
+                        if ui.button("Kapat").clicked() {
+                            self.visibility.show_accessories = false;
+                        }
+                    });
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Tell parent viewport that we should not show next frame:
+                    self.visibility.show_accessories = false;
+                }
+            },
+        );
+    }
 }
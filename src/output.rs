@@ -0,0 +1,133 @@
+use crate::error::AppError;
+use crate::escape::file_name_safe;
+use crate::label::LabelSize;
+use crate::settings::{self, Versioned};
+
+use chrono::prelude::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DocumentKind {
+    Maliyet,
+    Fiyat,
+    Tablo,
+    Malzeme,
+    IsEmri,
+    Etiket,
+    SatinAlma,
+    Tedarik,
+}
+
+impl DocumentKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DocumentKind::Maliyet => "maliyet",
+            DocumentKind::Fiyat => "fiyat",
+            DocumentKind::Tablo => "tablo",
+            DocumentKind::Malzeme => "malzeme",
+            DocumentKind::IsEmri => "is-emri",
+            DocumentKind::Etiket => "etiket",
+            DocumentKind::SatinAlma => "satin-alma",
+            DocumentKind::Tedarik => "tedarik",
+        }
+    }
+}
+
+/// Where generated documents are written and how they are named.
+///
+/// `file_name_template` understands `{tur}` (maliyet/fiyat/is-emri/...), `{no}` (document
+/// number), `{musteri}` (client name) and `{tarih}` (date).
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    pub documents_dir: String,
+    pub file_name_template: String,
+    pub per_client_folder: bool,
+    pub next_document_number: u32,
+    pub save_without_opening: bool,
+    /// Adds the cost per component to the consumables document.
+    pub include_cost_breakdown: bool,
+    /// Work orders get a page per pleat instead of one sheet for the batch.
+    pub work_order_per_pleat: bool,
+    pub label_size: LabelSize,
+    /// Printer device or share the ZPL labels are sent to, e.g. `/dev/usb/lp0`. Empty to
+    /// save them as a `.zpl` document instead.
+    pub label_printer: String,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        OutputSettings {
+            documents_dir: settings::default_documents_dir()
+                .to_string_lossy()
+                .to_string(),
+            file_name_template: "{tur}-{no}-{musteri}-{tarih}".to_string(),
+            per_client_folder: false,
+            next_document_number: 1,
+            save_without_opening: false,
+            include_cost_breakdown: false,
+            work_order_per_pleat: true,
+            label_size: LabelSize::Orta,
+            label_printer: "".to_string(),
+        }
+    }
+}
+
+impl Versioned for OutputSettings {
+    const FILE_NAME: &'static str = "output.json";
+    const VERSION: u32 = 1;
+}
+
+impl OutputSettings {
+    pub fn create_from_file() -> Result<Self, AppError> {
+        settings::load(Self::FILE_NAME)
+    }
+
+    pub fn to_file(&self) -> Result<(), AppError> {
+        settings::save(Self::FILE_NAME, self)
+    }
+
+    pub fn document_path(&self, kind: DocumentKind, client: &str, extension: &str) -> PathBuf {
+        let client = file_name_safe(client);
+        let client = if client.is_empty() {
+            "musteri".to_string()
+        } else {
+            client
+        };
+
+        let file_name = self
+            .file_name_template
+            .replace("{tur}", kind.name())
+            .replace("{no}", &format!("{:04}", self.next_document_number))
+            .replace("{musteri}", &client)
+            .replace("{tarih}", &Local::now().format("%Y-%m-%d").to_string());
+        let file_name = file_name_safe(&file_name);
+        let file_name = if file_name.is_empty() {
+            kind.name().to_string()
+        } else {
+            file_name
+        };
+
+        // A relative folder is taken relative to the data folder, not the working directory.
+        let mut path = settings::data_dir().join(&self.documents_dir);
+        if self.per_client_folder {
+            path.push(&client);
+        }
+        path.push(format!("{}.{}", file_name, extension));
+        path
+    }
+}
+
+/// Writes a generated document, creating its folder if needed.
+pub fn write_file(path: &Path, content: &[u8]) -> Result<(), AppError> {
+    let document_error = |source| AppError::Document {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(document_error)?;
+    }
+    fs::write(path, content).map_err(document_error)
+}
@@ -0,0 +1,96 @@
+//! A minimal PDF writer for documents that are drawn as images, such as labels.
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+/// One page showing a grayscale image over its whole area.
+pub struct Page {
+    pub width_mm: f32,
+    pub height_mm: f32,
+    pub pixel_width: u32,
+    pub pixel_height: u32,
+    /// One byte per pixel, 0 black to 255 white, rows from the top.
+    pub pixels: Vec<u8>,
+}
+
+fn points(mm: f32) -> f32 {
+    mm * 72. / 25.4
+}
+
+/// Writes the pages as a PDF, each page the size of its image.
+pub fn write(pages: &[Page]) -> Vec<u8> {
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    };
+    let stream = |dictionary: String, data: &[u8]| {
+        let mut body =
+            format!("<< {} /Length {} >>\nstream\n", dictionary, data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        body
+    };
+
+    // 1 is the catalogue, 2 the page tree, then a page, its contents and its image
+    // for every page.
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", 3 + 3 * i))
+        .collect();
+    object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    object(
+        &mut pdf,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .as_bytes(),
+    );
+    for (i, page) in pages.iter().enumerate() {
+        let (width, height) = (points(page.width_mm), points(page.height_mm));
+        let contents = 4 + 3 * i;
+        let image = 5 + 3 * i;
+        object(
+            &mut pdf,
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /XObject << /Im {} 0 R >> >> /Contents {} 0 R >>",
+                width, height, image, contents
+            )
+            .as_bytes(),
+        );
+        let draw = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im Do Q", width, height);
+        object(&mut pdf, &stream(String::new(), draw.as_bytes()));
+        object(
+            &mut pdf,
+            &stream(
+                format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                     /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+                    page.pixel_width, page.pixel_height
+                ),
+                &compress_to_vec_zlib(&page.pixels, 6),
+            ),
+        );
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes(),
+    );
+    for offset in &offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
@@ -7,11 +7,39 @@ pub enum PliseName {
     Ince,
 }
 
+impl PliseName {
+    pub const ALL: [PliseName; 3] = [PliseName::Klasik, PliseName::Genis, PliseName::Ince];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PliseName::Klasik => "Klasik",
+            PliseName::Genis => "Genis",
+            PliseName::Ince => "Ince",
+        }
+    }
+}
+
+/// The colour family of the profiles, each with its own metre price. The exact colour,
+/// a RAL or wood-grain code, is kept on the line.
 #[derive(Clone, Serialize, Deserialize, Copy, PartialEq)]
 pub enum ColorName {
     Beyaz,
+    /// Painted in one of the RAL colours kept in stock.
     Boya,
     Ahsap,
+    /// Powder-coated to order in any RAL colour, with a setup charge per colour.
+    Ozel,
+}
+
+impl ColorName {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorName::Beyaz => "Beyaz",
+            ColorName::Boya => "Boya",
+            ColorName::Ahsap => "Ahsap",
+            ColorName::Ozel => "Özel RAL",
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -97,3 +125,69 @@ impl PliseType {
         self.name
     }
 }
+
+/// The pleated mesh or fabric of a pleat.
+#[derive(Clone, Serialize, Deserialize, Copy, PartialEq, Default)]
+pub enum TulType {
+    /// Fibreglass mesh.
+    #[default]
+    Standart,
+    /// Pet-resistant mesh.
+    Evcil,
+    /// Anti-pollen mesh.
+    Polen,
+    /// Pleated blackout fabric.
+    Karartma,
+}
+
+impl TulType {
+    pub const ALL: [TulType; 4] = [
+        TulType::Standart,
+        TulType::Evcil,
+        TulType::Polen,
+        TulType::Karartma,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TulType::Standart => "Standart Tül",
+            TulType::Evcil => "Evcil Hayvan Tülü",
+            TulType::Polen => "Polen Tülü",
+            TulType::Karartma => "Karartma Kumaşı",
+        }
+    }
+
+    /// The price component, BOM item and catalogue part of the mesh.
+    pub fn key(&self) -> &'static str {
+        match self {
+            TulType::Standart => "tul",
+            TulType::Evcil => "evcil_tul",
+            TulType::Polen => "polen_tul",
+            TulType::Karartma => "karartma_tul",
+        }
+    }
+}
+
+/// How the pleat is fixed to the window.
+#[derive(Clone, Serialize, Deserialize, Copy, PartialEq, Default)]
+pub enum MountType {
+    /// Inside the reveal, the frame sits against its walls.
+    #[default]
+    Pervaz,
+    /// Screwed onto the face of the wall or window frame, overlapping the opening.
+    Yuzey,
+    /// Inside a reveal shallower than the frame, built out with an adapter profile.
+    Adaptor,
+}
+
+impl MountType {
+    pub const ALL: [MountType; 3] = [MountType::Pervaz, MountType::Yuzey, MountType::Adaptor];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MountType::Pervaz => "Pervaz İçi",
+            MountType::Yuzey => "Yüzeye",
+            MountType::Adaptor => "Adaptör Profilli",
+        }
+    }
+}
@@ -1,22 +1,302 @@
 use crate::consumable::Consumable;
-use crate::plise::{ColorName, PliseName};
+use crate::error::AppError;
+use crate::plise::{ColorName, PliseName, TulType};
+use crate::settings::{self, Versioned};
 
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::{env, fs::File, fs::OpenOptions};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ColorPrice {
     pub beyaz: f32,
     pub boya: f32,
     pub ahsap: f32,
+    pub ozel: f32,
 }
 
-#[derive(Serialize, Deserialize)]
+impl Default for ColorPrice {
+    fn default() -> Self {
+        ColorPrice {
+            beyaz: 120.,
+            boya: 130.,
+            ahsap: 140.,
+            ozel: 160.,
+        }
+    }
+}
+
+/// Price and roll width of a mesh or fabric.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TulPrice {
+    /// TL per m².
+    pub price: f32,
+    /// In cm. The mesh is pleated across the roll, so this is the tallest pleat it makes.
+    pub roll_width: f32,
+}
+
+impl Default for TulPrice {
+    fn default() -> Self {
+        TulPrice {
+            price: 30.,
+            roll_width: 250.,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TulPrices {
+    pub standart: TulPrice,
+    pub evcil: TulPrice,
+    pub polen: TulPrice,
+    pub karartma: TulPrice,
+}
+
+impl Default for TulPrices {
+    fn default() -> Self {
+        TulPrices {
+            standart: TulPrice::default(),
+            evcil: TulPrice {
+                price: 75.,
+                roll_width: 240.,
+            },
+            polen: TulPrice {
+                price: 60.,
+                roll_width: 250.,
+            },
+            karartma: TulPrice {
+                price: 120.,
+                roll_width: 220.,
+            },
+        }
+    }
+}
+
+impl TulPrices {
+    pub fn get(&self, tul: TulType) -> &TulPrice {
+        match tul {
+            TulType::Standart => &self.standart,
+            TulType::Evcil => &self.evcil,
+            TulType::Polen => &self.polen,
+            TulType::Karartma => &self.karartma,
+        }
+    }
+
+    pub fn get_mut(&mut self, tul: TulType) -> &mut TulPrice {
+        match tul {
+            TulType::Standart => &mut self.standart,
+            TulType::Evcil => &mut self.evcil,
+            TulType::Polen => &mut self.polen,
+            TulType::Karartma => &mut self.karartma,
+        }
+    }
+}
+
+/// How labour is costed: as a percentage of the material cost, from assembly time, or both.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum LabourMode {
+    Percentage,
+    Time,
+    Both,
+}
+
+impl LabourMode {
+    pub const ALL: [LabourMode; 3] = [LabourMode::Percentage, LabourMode::Time, LabourMode::Both];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LabourMode::Percentage => "Yüzde",
+            LabourMode::Time => "Montaj Süresi",
+            LabourMode::Both => "İkisi Birden",
+        }
+    }
+}
+
+/// Assembly time of one pleat of a series.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssemblyTime {
+    pub fixed_minutes: f32,
+    /// Per metre of kasa, kanat and adapter profile cut.
+    pub minutes_per_metre: f32,
+    /// Per köşe, teker, klips, stop and dönüş piece.
+    pub minutes_per_piece: f32,
+}
+
+impl Default for AssemblyTime {
+    fn default() -> Self {
+        AssemblyTime {
+            fixed_minutes: 15.,
+            minutes_per_metre: 2.,
+            minutes_per_piece: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LabourModel {
+    pub mode: LabourMode,
+    /// TL per hour of assembly.
+    pub hourly_rate: f32,
+    /// Workshop overhead spread over assembly hours, TL per hour.
+    pub overhead_rate: f32,
+    pub klasik: AssemblyTime,
+    pub genis: AssemblyTime,
+    pub ince: AssemblyTime,
+}
+
+impl Default for LabourModel {
+    fn default() -> Self {
+        LabourModel {
+            mode: LabourMode::Percentage,
+            hourly_rate: 150.,
+            overhead_rate: 0.,
+            klasik: AssemblyTime::default(),
+            genis: AssemblyTime {
+                fixed_minutes: 20.,
+                ..AssemblyTime::default()
+            },
+            ince: AssemblyTime {
+                fixed_minutes: 12.,
+                ..AssemblyTime::default()
+            },
+        }
+    }
+}
+
+impl LabourModel {
+    pub fn assembly_time(&self, plise_name: PliseName) -> &AssemblyTime {
+        match plise_name {
+            PliseName::Klasik => &self.klasik,
+            PliseName::Genis => &self.genis,
+            PliseName::Ince => &self.ince,
+        }
+    }
+}
+
+/// Margins for a kind of buyer, replacing the ones in `Price`, and component prices
+/// that differ from the price list, by component key.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceProfile {
+    pub name: String,
+    pub klasik_kar: f32,
+    pub genis_kar: f32,
+    pub ince_kar: f32,
+    pub overrides: BTreeMap<String, f32>,
+}
+
+impl Default for PriceProfile {
+    fn default() -> Self {
+        PriceProfile {
+            name: "Yeni Profil".to_string(),
+            klasik_kar: 20.,
+            genis_kar: 20.,
+            ince_kar: 20.,
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl PriceProfile {
+    fn new(name: &str, kar: f32) -> Self {
+        PriceProfile {
+            name: name.to_string(),
+            klasik_kar: kar,
+            genis_kar: kar,
+            ince_kar: kar,
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+/// An add-on a line can be ordered with, e.g. a magnetic closure or black cord.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessory {
+    /// Lines refer to the add-on by its name.
+    pub name: String,
+    /// The series it is offered for.
+    pub series: Vec<PliseName>,
+    /// TL per piece, or per metre for add-ons fitted along the height.
+    pub price: f32,
+    /// Pieces per pleat, or lengths of the pleat's height when `per_metre` is set.
+    pub quantity: f32,
+    pub per_metre: bool,
+    /// Where purchase orders buy it from. Add-ons have no part in the supplier catalogue.
+    pub supplier: String,
+    pub part_no: String,
+    /// Pieces per pack, or metres per roll when `per_metre` is set.
+    pub pack_size: f32,
+}
+
+impl Default for Accessory {
+    fn default() -> Self {
+        Accessory {
+            name: "Yeni Aksesuar".to_string(),
+            series: PliseName::ALL.to_vec(),
+            price: 0.,
+            quantity: 1.,
+            per_metre: false,
+            supplier: "Aksesuar Tedarikçisi".to_string(),
+            part_no: "".to_string(),
+            pack_size: 1.,
+        }
+    }
+}
+
+impl Accessory {
+    fn new(name: &str, series: &[PliseName], price: f32, quantity: f32, per_metre: bool) -> Self {
+        Accessory {
+            name: name.to_string(),
+            series: series.to_vec(),
+            price,
+            quantity,
+            per_metre,
+            ..Accessory::default()
+        }
+    }
+
+    pub fn offered_for(&self, plise_name: PliseName) -> bool {
+        self.series.contains(&plise_name)
+    }
+
+    /// The quantity for one pleat, in the unit of [`Accessory::unit`].
+    pub fn quantity(&self, consumable: &Consumable) -> f32 {
+        if self.per_metre {
+            self.quantity * consumable.height
+        } else {
+            self.quantity
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        if self.per_metre {
+            "cm"
+        } else {
+            "adet"
+        }
+    }
+
+    pub fn cost(&self, consumable: &Consumable) -> f32 {
+        if self.per_metre {
+            self.quantity(consumable) * self.price / 100.
+        } else {
+            self.quantity(consumable) * self.price
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Price {
     pub plise_name: PliseName,
     pub color_price: ColorPrice,
-    pub tul_price: f32,
+    pub tul: TulPrices,
     pub serit_price: f32,
     pub teker_price: f32,
     pub klips_price: f32,
@@ -25,23 +305,29 @@ pub struct Price {
     pub klasik_kose_price: f32,
     pub genis_kose_price: f32,
     pub ince_kose_price: f32,
+    /// Adapter profile for shallow reveals, TL per metre.
+    pub adaptor_price: f32,
+    /// Powder-coating setup charge for each custom RAL colour in an order, TL.
+    pub boya_kurulum: f32,
     pub klasik_kar: f32,
     pub genis_kar: f32,
     pub ince_kar: f32,
+    /// Labour as a percentage of the material cost, used unless `labour` is time based only.
     pub isci_maliyeti: f32,
+    pub labour: LabourModel,
     pub kdv: f32,
+    /// Lines earning less than this margin are flagged by the target price calculator.
+    pub min_kar: f32,
+    pub profiles: Vec<PriceProfile>,
+    pub accessories: Vec<Accessory>,
 }
 
 impl Default for Price {
     fn default() -> Self {
         Price {
             plise_name: PliseName::Klasik,
-            color_price: ColorPrice {
-                beyaz: 120.,
-                boya: 130.,
-                ahsap: 140.,
-            },
-            tul_price: 30.,
+            color_price: ColorPrice::default(),
+            tul: TulPrices::default(),
             serit_price: 3.,
             teker_price: 2.5,
             klips_price: 1.,
@@ -50,52 +336,374 @@ impl Default for Price {
             klasik_kose_price: 1.,
             genis_kose_price: 1.,
             ince_kose_price: 4.5,
+            adaptor_price: 60.,
+            boya_kurulum: 750.,
             klasik_kar: 20.,
             genis_kar: 20.,
             ince_kar: 20.,
             isci_maliyeti: 30.,
+            labour: LabourModel::default(),
             kdv: 20.,
+            min_kar: 10.,
+            profiles: vec![
+                PriceProfile::new("Perakende", 20.),
+                PriceProfile::new("Bayi", 10.),
+                PriceProfile::new("Müteahhit", 15.),
+            ],
+            accessories: vec![
+                Accessory::new("Uzun Kulp", &PliseName::ALL, 35., 1., false),
+                Accessory::new(
+                    "Mıknatıslı Kapama",
+                    &[PliseName::Klasik, PliseName::Genis],
+                    45.,
+                    1.,
+                    false,
+                ),
+                Accessory::new("Fırça Conta", &PliseName::ALL, 8., 2., true),
+                Accessory::new("Siyah İp", &PliseName::ALL, 10., 1., false),
+            ],
         }
     }
 }
 
-impl Price {
-    pub fn create_from_file() -> Self {
-        let mut exe_path = env::current_exe().unwrap();
-        exe_path.set_file_name("prices.json");
+/// `prices.json` history:
+/// - 0: flat list of component prices with a single `aluminyum` price for every colour.
+/// - 1: `Price` written as is, without a version field.
+/// - 2: versioned; fields missing from the file take their default value.
+/// - 3: a price and roll width per mesh type instead of a single `tul_price`.
+impl Versioned for Price {
+    const FILE_NAME: &'static str = "prices.json";
+    const VERSION: u32 = 3;
 
-        if !exe_path.exists() {
-            let mut file = File::create(&exe_path).unwrap();
-            file.write_all(serde_json::to_string(&Price::default()).unwrap().as_bytes())
-                .unwrap();
+    fn detect_version(value: &Value) -> u32 {
+        match value.get("version").and_then(Value::as_u64) {
+            Some(version) => version as u32,
+            None if value.get("aluminyum").is_some() => 0,
+            None => 1,
+        }
+    }
+
+    fn migrate(version: u32, value: Value) -> Value {
+        match version {
+            0 => {
+                let aluminyum = &value["aluminyum"];
+                json!({
+                    "color_price": {
+                        "beyaz": aluminyum,
+                        "boya": aluminyum,
+                        "ahsap": aluminyum,
+                    },
+                    "tul_price": value["tul"],
+                    "serit_price": value["serit"],
+                    "teker_price": value["teker"],
+                    "klips_price": value["klips"],
+                    "stop_price": value["stop"],
+                    "klasik_kose_price": value["klasik_kose"],
+                    "genis_kose_price": value["genis_kose"],
+                    "ince_kose_price": value["ince_kose"],
+                })
+            }
+            2 => {
+                let mut value = value;
+                if let Some(price) = value
+                    .as_object_mut()
+                    .and_then(|fields| fields.remove("tul_price"))
+                {
+                    value["tul"] = json!({
+                        "standart": {
+                            "price": price,
+                            "roll_width": TulPrice::default().roll_width,
+                        },
+                    });
+                }
+                value
+            }
+            _ => value,
         }
+    }
+}
 
-        let mut file = File::open(&exe_path).unwrap();
-        let mut buffer = String::new();
-        file.read_to_string(&mut buffer).unwrap();
-        let data = serde_json::from_str(&buffer).unwrap();
+/// Cost of a line and the share of a target price it would get.
+pub struct LineMargin {
+    pub line: usize,
+    pub plise_name: PliseName,
+    pub maliyet: f32,
+    pub price: f32,
+}
+
+/// The same, summed over the lines of one series.
+pub struct SeriesMargin {
+    pub plise_name: PliseName,
+    pub count: usize,
+    pub maliyet: f32,
+    pub price: f32,
+}
+
+/// Margin in percent over cost.
+pub fn kar_percent(maliyet: f32, price: f32) -> f32 {
+    if maliyet > 0. {
+        (price / maliyet - 1.) * 100.
+    } else {
+        0.
+    }
+}
+
+/// Costs of an order split by component, per line and in total.
+pub struct CostBreakdown {
+    pub labels: Vec<&'static str>,
+    pub lines: Vec<Vec<f32>>,
+    pub totals: Vec<f32>,
+    pub total: f32,
+}
+
+/// A priced component, as listed in the catalogue.
+pub struct Component {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub value: f32,
+}
+
+impl Price {
+    pub fn create_from_file() -> Result<Self, AppError> {
+        settings::load(Self::FILE_NAME)
+    }
+
+    pub fn to_file(&self) -> Result<(), AppError> {
+        settings::save(Self::FILE_NAME, self)
+    }
+
+    pub fn components(&self) -> Vec<Component> {
+        let component = |key, label, unit, value| Component {
+            key,
+            label,
+            unit,
+            value,
+        };
+        vec![
+            component("beyaz", "Beyaz Profil", "TL/m", self.color_price.beyaz),
+            component("boya", "Boyalı Profil", "TL/m", self.color_price.boya),
+            component("ahsap", "Ahşap Profil", "TL/m", self.color_price.ahsap),
+            component("ozel", "Özel RAL Profil", "TL/m", self.color_price.ozel),
+            component("tul", "Standart Tül", "TL/m²", self.tul.standart.price),
+            component(
+                "evcil_tul",
+                "Evcil Hayvan Tülü",
+                "TL/m²",
+                self.tul.evcil.price,
+            ),
+            component("polen_tul", "Polen Tülü", "TL/m²", self.tul.polen.price),
+            component(
+                "karartma_tul",
+                "Karartma Kumaşı",
+                "TL/m²",
+                self.tul.karartma.price,
+            ),
+            component("serit", "Şerit", "TL/m", self.serit_price),
+            component("teker", "Teker", "TL/adet", self.teker_price),
+            component("klips", "Klips", "TL/adet", self.klips_price),
+            component("stop", "Stop", "TL/adet", self.stop_price),
+            component("donus", "Dönüş", "TL/adet", self.donus_price),
+            component("ince_kose", "İnce Köşe", "TL/adet", self.ince_kose_price),
+            component(
+                "klasik_kose",
+                "Klasik Köşe",
+                "TL/adet",
+                self.klasik_kose_price,
+            ),
+            component("genis_kose", "Geniş Köşe", "TL/adet", self.genis_kose_price),
+            component("adaptor", "Adaptör Profil", "TL/m", self.adaptor_price),
+        ]
+    }
+
+    /// Margins, labour and KDV.
+    pub fn rates(&self) -> Vec<Component> {
+        let rate = |key, label, unit, value| Component {
+            key,
+            label,
+            unit,
+            value,
+        };
+        vec![
+            rate("klasik_kar", "Klasik Seri Kar", "%", self.klasik_kar),
+            rate("genis_kar", "Geniş Seri Kar", "%", self.genis_kar),
+            rate("ince_kar", "İnce Seri Kar", "%", self.ince_kar),
+            rate("isci_maliyeti", "İşçi Maliyeti", "%", self.isci_maliyeti),
+            rate(
+                "saat_ucreti",
+                "Saat Ücreti",
+                "TL/saat",
+                self.labour.hourly_rate,
+            ),
+            rate(
+                "genel_gider",
+                "Genel Gider",
+                "TL/saat",
+                self.labour.overhead_rate,
+            ),
+            rate(
+                "boya_kurulum",
+                "Özel RAL Kurulum",
+                "TL/renk",
+                self.boya_kurulum,
+            ),
+            rate("kdv", "Kdv", "%", self.kdv),
+            rate("min_kar", "Asgari Kar", "%", self.min_kar),
+        ]
+    }
+
+    /// Assembly times and mesh roll widths, which are not prices but go with them.
+    pub fn measures(&self) -> Vec<Component> {
+        let measure = |key, label, unit, value| Component {
+            key,
+            label,
+            unit,
+            value,
+        };
+        let (klasik, genis, ince) = (&self.labour.klasik, &self.labour.genis, &self.labour.ince);
+        vec![
+            measure(
+                "klasik_sabit",
+                "Klasik Sabit Süre",
+                "dk",
+                klasik.fixed_minutes,
+            ),
+            measure(
+                "klasik_metre",
+                "Klasik Profil Metresi",
+                "dk",
+                klasik.minutes_per_metre,
+            ),
+            measure(
+                "klasik_parca",
+                "Klasik Parça Başına",
+                "dk",
+                klasik.minutes_per_piece,
+            ),
+            measure("genis_sabit", "Geniş Sabit Süre", "dk", genis.fixed_minutes),
+            measure(
+                "genis_metre",
+                "Geniş Profil Metresi",
+                "dk",
+                genis.minutes_per_metre,
+            ),
+            measure(
+                "genis_parca",
+                "Geniş Parça Başına",
+                "dk",
+                genis.minutes_per_piece,
+            ),
+            measure("ince_sabit", "İnce Sabit Süre", "dk", ince.fixed_minutes),
+            measure(
+                "ince_metre",
+                "İnce Profil Metresi",
+                "dk",
+                ince.minutes_per_metre,
+            ),
+            measure(
+                "ince_parca",
+                "İnce Parça Başına",
+                "dk",
+                ince.minutes_per_piece,
+            ),
+            measure(
+                "tul_en",
+                "Standart Tül Rulo Eni",
+                "cm",
+                self.tul.standart.roll_width,
+            ),
+            measure(
+                "evcil_tul_en",
+                "Evcil Hayvan Tülü Rulo Eni",
+                "cm",
+                self.tul.evcil.roll_width,
+            ),
+            measure(
+                "polen_tul_en",
+                "Polen Tülü Rulo Eni",
+                "cm",
+                self.tul.polen.roll_width,
+            ),
+            measure(
+                "karartma_tul_en",
+                "Karartma Kumaşı Rulo Eni",
+                "cm",
+                self.tul.karartma.roll_width,
+            ),
+        ]
+    }
 
-        data
+    /// Sets the component, rate or measure with the given key, as listed by `components`,
+    /// `rates` and `measures`.
+    pub fn set(&mut self, key: &str, value: f32) {
+        let field = match key {
+            "beyaz" => &mut self.color_price.beyaz,
+            "boya" => &mut self.color_price.boya,
+            "ahsap" => &mut self.color_price.ahsap,
+            "ozel" => &mut self.color_price.ozel,
+            "boya_kurulum" => &mut self.boya_kurulum,
+            "tul" => &mut self.tul.standart.price,
+            "evcil_tul" => &mut self.tul.evcil.price,
+            "polen_tul" => &mut self.tul.polen.price,
+            "karartma_tul" => &mut self.tul.karartma.price,
+            "serit" => &mut self.serit_price,
+            "teker" => &mut self.teker_price,
+            "klips" => &mut self.klips_price,
+            "stop" => &mut self.stop_price,
+            "donus" => &mut self.donus_price,
+            "ince_kose" => &mut self.ince_kose_price,
+            "klasik_kose" => &mut self.klasik_kose_price,
+            "genis_kose" => &mut self.genis_kose_price,
+            "adaptor" => &mut self.adaptor_price,
+            "klasik_kar" => &mut self.klasik_kar,
+            "genis_kar" => &mut self.genis_kar,
+            "ince_kar" => &mut self.ince_kar,
+            "isci_maliyeti" => &mut self.isci_maliyeti,
+            "saat_ucreti" => &mut self.labour.hourly_rate,
+            "genel_gider" => &mut self.labour.overhead_rate,
+            "kdv" => &mut self.kdv,
+            "min_kar" => &mut self.min_kar,
+            "klasik_sabit" => &mut self.labour.klasik.fixed_minutes,
+            "klasik_metre" => &mut self.labour.klasik.minutes_per_metre,
+            "klasik_parca" => &mut self.labour.klasik.minutes_per_piece,
+            "genis_sabit" => &mut self.labour.genis.fixed_minutes,
+            "genis_metre" => &mut self.labour.genis.minutes_per_metre,
+            "genis_parca" => &mut self.labour.genis.minutes_per_piece,
+            "ince_sabit" => &mut self.labour.ince.fixed_minutes,
+            "ince_metre" => &mut self.labour.ince.minutes_per_metre,
+            "ince_parca" => &mut self.labour.ince.minutes_per_piece,
+            "tul_en" => &mut self.tul.standart.roll_width,
+            "evcil_tul_en" => &mut self.tul.evcil.roll_width,
+            "polen_tul_en" => &mut self.tul.polen.roll_width,
+            "karartma_tul_en" => &mut self.tul.karartma.roll_width,
+            _ => return,
+        };
+        *field = value;
     }
 
-    pub fn to_file(&self) {
-        let mut exe_path = env::current_exe().unwrap();
-        exe_path.set_file_name("prices.json");
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&exe_path)
-            .unwrap();
-        file.write_all(serde_json::to_string(&self).unwrap().as_bytes())
-            .unwrap();
+    /// The prices with the named profile applied. An empty or unknown name leaves
+    /// them as they are.
+    pub fn with_profile(&self, name: &str) -> Price {
+        let mut price = self.clone();
+        if let Some(profile) = self.profiles.iter().find(|profile| profile.name == name) {
+            price.klasik_kar = profile.klasik_kar;
+            price.genis_kar = profile.genis_kar;
+            price.ince_kar = profile.ince_kar;
+            for (key, value) in &profile.overrides {
+                price.set(key, *value);
+            }
+        }
+        price
     }
 
-    fn calculate_single_price(&self, consumable: &Consumable) -> f32 {
+    /// Cost of a single pleat split by component, labour and the line's share of the
+    /// powder-coating setup charge (see [`Price::setup_shares`]) last.
+    pub fn cost_breakdown(&self, consumable: &Consumable, setup: f32) -> Vec<(&'static str, f32)> {
         let alum_price = match consumable.get_plise_color() {
             ColorName::Beyaz => self.color_price.beyaz,
             ColorName::Boya => self.color_price.boya,
             ColorName::Ahsap => self.color_price.ahsap,
+            ColorName::Ozel => self.color_price.ozel,
         };
 
         let kose_price = match consumable.get_plise_name() {
@@ -106,47 +714,459 @@ impl Price {
 
         let kasa_maliyet = consumable.get_kasa_cm() * alum_price / 100.;
         let kanat_maliyet = consumable.get_kanat_cm() * alum_price / 100.;
-        let tul_maliyet = consumable.get_tul_cm_squared() * self.tul_price / 10000.;
+        let tul_price = self.tul.get(consumable.tul).price;
+        let tul_maliyet = consumable.get_tul_cm_squared() * tul_price / 10000.;
         let serit_maliyet = consumable.get_serit_cm() * self.serit_price / 100.;
         let kose_maliyet = consumable.get_kose_adet() * kose_price;
+        let adaptor_maliyet = consumable.get_adaptor_cm() * self.adaptor_price / 100.;
+        let aksesuar_maliyet = self
+            .accessories_of(consumable)
+            .fold(0., |sum, accessory| sum + accessory.cost(consumable));
 
         let teker_maliyet = consumable.get_teker_adet() * self.teker_price;
         let klips_maliyet = consumable.get_klips_adet() * self.klips_price;
         let stop_maliyet = consumable.get_stop_adet() * self.stop_price;
         let donus_maliyet = consumable.get_donus_adet() * self.donus_price;
 
-        let mut sum_maliyet = kasa_maliyet
-            + kanat_maliyet
-            + tul_maliyet
-            + serit_maliyet
-            + kose_maliyet
-            + teker_maliyet
-            + klips_maliyet
-            + stop_maliyet
-            + donus_maliyet;
+        let mut parts = vec![
+            ("Kasa", kasa_maliyet),
+            ("Kanat", kanat_maliyet),
+            ("Tül", tul_maliyet),
+            ("Şerit", serit_maliyet),
+            ("Köşe", kose_maliyet),
+            ("Teker", teker_maliyet),
+            ("Klips", klips_maliyet),
+            ("Stop", stop_maliyet),
+            ("Dönüş", donus_maliyet),
+            ("Adaptör", adaptor_maliyet),
+            ("Aksesuar", aksesuar_maliyet),
+        ];
+        let sum_maliyet: f32 = parts.iter().map(|(_, value)| value).sum();
+        let (percentage, time) = match self.labour.mode {
+            LabourMode::Percentage => (true, false),
+            LabourMode::Time => (false, true),
+            LabourMode::Both => (true, true),
+        };
+        let isci = if percentage {
+            sum_maliyet * self.isci_maliyeti / 100.
+        } else {
+            0.
+        };
+        let montaj = if time {
+            self.assembly_minutes(consumable) / 60.
+                * (self.labour.hourly_rate + self.labour.overhead_rate)
+        } else {
+            0.
+        };
+        parts.push(("İşçilik", isci));
+        parts.push(("Montaj", montaj));
+        parts.push(("Boya Kurulum", setup));
+        parts
+    }
 
-        sum_maliyet *= 1. + self.isci_maliyeti / 100.;
-        sum_maliyet
+    /// Each line's share of the powder-coating setup charge. The charge is made once for
+    /// every custom RAL code in the order and split over the lines in that colour by
+    /// their profile length.
+    pub fn setup_shares(&self, consumables: &[Consumable], item_count: u32) -> Vec<f32> {
+        let lines = consumables.iter().take(item_count as usize);
+        let custom = |line: &Consumable| line.get_plise_color() == ColorName::Ozel;
+        let mut profile_cm: BTreeMap<String, f32> = BTreeMap::new();
+        for line in lines.clone().filter(|line| custom(line)) {
+            *profile_cm.entry(line.color_code()).or_default() += line.get_profile_length_cm();
+        }
+        lines
+            .map(|line| match profile_cm.get(&line.color_code()) {
+                Some(total) if custom(line) && *total > 0. => {
+                    self.boya_kurulum * line.get_profile_length_cm() / total
+                }
+                _ => 0.,
+            })
+            .collect()
     }
 
-    pub fn calculate_prices(&self, consumables: &Vec<Consumable>, item_count: u32) -> (f32, f32, f32) {
-        let mut maliyet = 0.;
-        let mut total_price = 0.;
+    /// The add-ons chosen for a line that are offered for its series.
+    pub fn accessories_of<'a>(
+        &'a self,
+        consumable: &'a Consumable,
+    ) -> impl Iterator<Item = &'a Accessory> {
+        self.accessories.iter().filter(|accessory| {
+            accessory.offered_for(consumable.get_plise_name())
+                && consumable.aksesuarlar.contains(&accessory.name)
+        })
+    }
+
+    /// The component of [`Price::components`] an item of the BOM is bought as, e.g. the
+    /// profile of the pleat's colour for `kasa`.
+    pub fn bom_component(item: &str, consumable: &Consumable) -> Option<&'static str> {
+        Some(match item {
+            "kasa" | "kanat" => match consumable.get_plise_color() {
+                ColorName::Beyaz => "beyaz",
+                ColorName::Boya => "boya",
+                ColorName::Ahsap => "ahsap",
+                ColorName::Ozel => "ozel",
+            },
+            "kose" => match consumable.get_plise_name() {
+                PliseName::Klasik => "klasik_kose",
+                PliseName::Genis => "genis_kose",
+                PliseName::Ince => "ince_kose",
+            },
+            "tul" | "evcil_tul" | "polen_tul" | "karartma_tul" => consumable.tul.key(),
+            "serit" => "serit",
+            "teker" => "teker",
+            "klips" => "klips",
+            "stop" => "stop",
+            "donus" => "donus",
+            "adaptor" => "adaptor",
+            _ => return None,
+        })
+    }
 
-        for i in 0..item_count {
-            maliyet += self.calculate_single_price(&consumables[i as usize]);
-            let kar = match consumables[i as usize].get_plise_name() {
-                PliseName::Klasik => self.klasik_kar,
-                PliseName::Genis => self.genis_kar,
-                PliseName::Ince => self.ince_kar,
+    /// Minutes needed to assemble a single pleat.
+    pub fn assembly_minutes(&self, consumable: &Consumable) -> f32 {
+        let time = self.labour.assembly_time(consumable.get_plise_name());
+        let metres = consumable.get_profile_length_cm() / 100.;
+        let pieces = consumable.get_kose_adet()
+            + consumable.get_teker_adet()
+            + consumable.get_klips_adet()
+            + consumable.get_stop_adet()
+            + consumable.get_donus_adet();
+        time.fixed_minutes + metres * time.minutes_per_metre + pieces * time.minutes_per_piece
+    }
 
-            };
+    pub fn order_breakdown(&self, consumables: &[Consumable], item_count: u32) -> CostBreakdown {
+        let mut breakdown = CostBreakdown {
+            labels: Vec::new(),
+            lines: Vec::new(),
+            totals: Vec::new(),
+            total: 0.,
+        };
+        let shares = self.setup_shares(consumables, item_count);
+        for (consumable, setup) in consumables.iter().zip(shares) {
+            let parts = self.cost_breakdown(consumable, setup);
+            if breakdown.labels.is_empty() {
+                breakdown.labels = parts.iter().map(|(label, _)| *label).collect();
+                breakdown.totals = vec![0.; parts.len()];
+            }
+            for (total, (_, value)) in breakdown.totals.iter_mut().zip(&parts) {
+                *total += value;
+            }
+            breakdown.total += parts.iter().map(|(_, value)| value).sum::<f32>();
+            breakdown
+                .lines
+                .push(parts.into_iter().map(|(_, value)| value).collect());
+        }
+        breakdown
+    }
+
+    fn calculate_single_price(&self, consumable: &Consumable, setup: f32) -> f32 {
+        self.cost_breakdown(consumable, setup)
+            .iter()
+            .map(|(_, value)| value)
+            .sum()
+    }
 
-            total_price += maliyet * (1. + kar / 100.)
+    fn kar(&self, consumable: &Consumable) -> f32 {
+        match consumable.get_plise_name() {
+            PliseName::Klasik => self.klasik_kar,
+            PliseName::Genis => self.genis_kar,
+            PliseName::Ince => self.ince_kar,
+        }
+    }
+
+    /// Cost and price (without KDV) of a single pleat, with its share of the setup charge.
+    pub fn calculate_line(&self, consumable: &Consumable, setup: f32) -> (f32, f32) {
+        let maliyet = self.calculate_single_price(consumable, setup);
+        (maliyet, maliyet * (1. + self.kar(consumable) / 100.))
+    }
+
+    /// Cost, price and price with KDV of an order. Each line's margin applies to that
+    /// line's cost only, so the total is the sum of the line prices and does not depend
+    /// on the order of the lines.
+    pub fn calculate_prices(&self, consumables: &[Consumable], item_count: u32) -> (f32, f32, f32) {
+        let mut maliyet = 0.;
+        let mut total_price = 0.;
+
+        let shares = self.setup_shares(consumables, item_count);
+        for (consumable, setup) in consumables.iter().zip(shares) {
+            let (line_maliyet, line_price) = self.calculate_line(consumable, setup);
+            maliyet += line_maliyet;
+            total_price += line_price;
         }
 
         let total_price_kdv = total_price * (1. + self.kdv / 100.);
 
         (maliyet, total_price, total_price_kdv)
     }
+
+    /// Splits a target total (without KDV) across the lines in proportion to their
+    /// list prices, so the series keep their relative margins.
+    pub fn target_margins(
+        &self,
+        consumables: &[Consumable],
+        item_count: u32,
+        target: f32,
+    ) -> Vec<LineMargin> {
+        let shares = self.setup_shares(consumables, item_count);
+        let lines: Vec<_> = consumables
+            .iter()
+            .zip(shares)
+            .map(|(consumable, setup)| (consumable, self.calculate_line(consumable, setup)))
+            .collect();
+        let total_price: f32 = lines.iter().map(|(_, (_, price))| price).sum();
+        let total_maliyet: f32 = lines.iter().map(|(_, (maliyet, _))| maliyet).sum();
+
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, (consumable, (maliyet, price)))| {
+                let share = if total_price > 0. {
+                    price / total_price
+                } else if total_maliyet > 0. {
+                    maliyet / total_maliyet
+                } else {
+                    0.
+                };
+                LineMargin {
+                    line: i + 1,
+                    plise_name: consumable.get_plise_name(),
+                    maliyet: *maliyet,
+                    price: target * share,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sums line margins per series, in the order the series first appear.
+pub fn series_margins(lines: &[LineMargin]) -> Vec<SeriesMargin> {
+    let mut series: Vec<SeriesMargin> = Vec::new();
+    for line in lines {
+        match series.iter_mut().find(|s| s.plise_name == line.plise_name) {
+            Some(s) => {
+                s.maliyet += line.maliyet;
+                s.price += line.price;
+                s.count += 1;
+            }
+            None => series.push(SeriesMargin {
+                count: 1,
+                plise_name: line.plise_name,
+                maliyet: line.maliyet,
+                price: line.price,
+            }),
+        }
+    }
+    series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn pleat(plise_name: PliseName, width: f32, height: f32) -> Consumable {
+        let mut consumable = Consumable {
+            width,
+            height,
+            ..Consumable::default()
+        };
+        consumable.set_plise_name(plise_name);
+        consumable
+    }
+
+    fn cost(price: &Price, consumable: &Consumable) -> f32 {
+        price
+            .cost_breakdown(consumable, 0.)
+            .iter()
+            .map(|(_, value)| value)
+            .sum()
+    }
+
+    fn assert_close(left: f32, right: f32) {
+        assert!((left - right).abs() < 0.01, "{} != {}", left, right);
+    }
+
+    #[test]
+    fn margin_applies_to_each_line_cost() {
+        let price = Price {
+            ince_kar: 10.,
+            klasik_kar: 20.,
+            genis_kar: 40.,
+            kdv: 20.,
+            ..Price::default()
+        };
+        let lines = vec![
+            pleat(PliseName::Klasik, 100., 200.),
+            pleat(PliseName::Ince, 60., 120.),
+            pleat(PliseName::Genis, 180., 220.),
+        ];
+        let costs: Vec<f32> = lines.iter().map(|line| cost(&price, line)).collect();
+
+        let (maliyet, total_price, total_price_kdv) = price.calculate_prices(&lines, 3);
+        assert_close(maliyet, costs.iter().sum());
+        assert_close(
+            total_price,
+            costs[0] * 1.2 + costs[1] * 1.1 + costs[2] * 1.4,
+        );
+        assert_close(total_price_kdv, total_price * 1.2);
+
+        // The line order must not change the total.
+        let reversed: Vec<Consumable> = lines.iter().rev().cloned().collect();
+        assert_close(price.calculate_prices(&reversed, 3).1, total_price);
+    }
+
+    #[test]
+    fn lines_past_item_count_are_ignored() {
+        let price = Price::default();
+        let lines = vec![pleat(PliseName::Klasik, 100., 200.); 3];
+        let (_, one, _) = price.calculate_prices(&lines, 1);
+        let (_, three, _) = price.calculate_prices(&lines, 3);
+        assert_close(three, 3. * one);
+        assert_close(one, price.calculate_line(&lines[0], 0.).1);
+    }
+
+    fn upgrade(fixture: &str) -> Price {
+        let value = serde_json::from_str(fixture).unwrap();
+        settings::upgrade(Path::new(Price::FILE_NAME), value).unwrap()
+    }
+
+    #[test]
+    fn migrates_v0_aluminyum_prices() {
+        let price = upgrade(include_str!("fixtures/prices.v0.json"));
+        assert_eq!(price.color_price.beyaz, 120.);
+        assert_eq!(price.color_price.boya, 120.);
+        assert_eq!(price.color_price.ahsap, 120.);
+        assert_eq!(price.color_price.ozel, ColorPrice::default().ozel);
+        assert_eq!(price.tul.standart.price, 30.);
+        assert_eq!(price.tul.standart.roll_width, 250.);
+        assert_eq!(price.serit_price, 3.);
+        assert_eq!(price.ince_kose_price, 1.);
+        assert_eq!(price.genis_kose_price, 4.5);
+        assert_eq!(price.teker_price, 2.5);
+        assert_eq!(price.klasik_kar, Price::default().klasik_kar);
+    }
+
+    #[test]
+    fn migrates_unversioned_v1_prices() {
+        let price = upgrade(include_str!("fixtures/prices.v1.json"));
+        assert_eq!(price.color_price.beyaz, 125.);
+        assert_eq!(price.color_price.ahsap, 145.);
+        assert_eq!(price.tul.standart.price, 32.5);
+        assert_eq!(
+            price.tul.karartma.price,
+            TulPrices::default().karartma.price
+        );
+        assert_eq!(price.klasik_kar, 25.);
+        assert_eq!(price.adaptor_price, Price::default().adaptor_price);
+        assert!(!price.accessories.is_empty());
+    }
+
+    #[test]
+    fn migrates_v2_tul_price() {
+        let price = upgrade(include_str!("fixtures/prices.v2.json"));
+        assert_eq!(price.tul.standart.price, 42.5);
+        assert_eq!(
+            price.tul.standart.roll_width,
+            TulPrice::default().roll_width
+        );
+        assert_eq!(price.tul.evcil.price, TulPrices::default().evcil.price);
+        assert_eq!(price.serit_price, 3.5);
+        assert_eq!(price.genis_kar, 30.);
+        assert_eq!(price.kdv, 18.);
+        assert_eq!(price.min_kar, 5.);
+    }
+
+    #[test]
+    fn reads_v3_prices_as_is() {
+        let price = upgrade(include_str!("fixtures/prices.v3.json"));
+        assert!(price.plise_name == PliseName::Genis);
+        assert_eq!(price.tul.standart.price, 31.);
+        assert_eq!(price.tul.karartma.price, 130.);
+        assert_eq!(price.tul.karartma.roll_width, 200.);
+        assert_eq!(price.tul.polen.price, TulPrices::default().polen.price);
+        assert_eq!(price.adaptor_price, 65.);
+        assert_eq!(price.boya_kurulum, Price::default().boya_kurulum);
+    }
+
+    #[test]
+    fn rejects_newer_prices() {
+        let value = json!({ "version": Price::VERSION + 1 });
+        let result = settings::upgrade::<Price>(Path::new(Price::FILE_NAME), value);
+        assert!(
+            matches!(result, Err(AppError::Version { version, .. }) if version == Price::VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn load_keeps_a_backup_of_older_files() {
+        let dir = settings::init_test_data_dir();
+        let fixture = include_str!("fixtures/prices.v2.json");
+        std::fs::write(dir.join("eski-prices.json"), fixture).unwrap();
+
+        let price: Price = settings::load("eski-prices.json").unwrap();
+        assert_eq!(price.tul.standart.price, 42.5);
+        let backup = std::fs::read_to_string(dir.join("eski-prices.json.v2.bak")).unwrap();
+        assert_eq!(backup, fixture);
+        let rewritten: Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("eski-prices.json")).unwrap())
+                .unwrap();
+        assert_eq!(rewritten["version"], Price::VERSION);
+        assert!(rewritten.get("tul_price").is_none());
+
+        // The current version is read without another backup.
+        let _: Price = settings::load("eski-prices.json").unwrap();
+        assert!(!dir
+            .join(format!("eski-prices.json.v{}.bak", Price::VERSION))
+            .exists());
+    }
+
+    #[test]
+    fn assembly_time_follows_cut_length() {
+        let time = AssemblyTime {
+            fixed_minutes: 10.,
+            minutes_per_metre: 2.,
+            minutes_per_piece: 0.5,
+        };
+        let mut price = Price::default();
+        price.labour.klasik = time.clone();
+        price.labour.genis = time.clone();
+        price.labour.ince = time;
+        // 150 cm of kasa and 32 cm of kanat, 14 pieces, whatever the series weighs.
+        for plise_name in PliseName::ALL {
+            let consumable = pleat(plise_name, 40., 40.);
+            assert_close(
+                price.assembly_minutes(&consumable),
+                10. + 1.82 * 2. + 14. * 0.5,
+            );
+        }
+    }
+
+    #[test]
+    fn setup_charge_is_split_by_profile_length() {
+        let price = Price::default();
+        let custom = |plise_name, width, height, code: &str| {
+            let mut consumable = pleat(plise_name, width, height);
+            consumable.set_plise_color(ColorName::Ozel);
+            consumable.renk_kodu = code.to_string();
+            consumable
+        };
+        let lines = vec![
+            custom(PliseName::Ince, 40., 40., "ral 7016"),
+            custom(PliseName::Genis, 50., 40., "RAL  7016"),
+            custom(PliseName::Klasik, 80., 100., "RAL 9005"),
+            pleat(PliseName::Klasik, 80., 100.),
+        ];
+        let shares = price.setup_shares(&lines, 4);
+        let (ince, genis) = (
+            lines[0].get_profile_length_cm(),
+            lines[1].get_profile_length_cm(),
+        );
+        assert_close(shares[0], 750. * ince / (ince + genis));
+        assert_close(shares[1], 750. * genis / (ince + genis));
+        assert_close(shares[2], 750.);
+        // The series weights would have split it differently.
+        let weighted = |line: &Consumable| line.get_kasa_cm() + line.get_kanat_cm();
+        let ince_weighted = weighted(&lines[0]) / (weighted(&lines[0]) + weighted(&lines[1]));
+        assert!((shares[0] / 750. - ince_weighted).abs() > 0.01);
+        assert_eq!(shares[3], 0.);
+    }
 }
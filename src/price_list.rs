@@ -0,0 +1,568 @@
+use crate::error::AppError;
+use crate::output;
+use crate::price::{Accessory, LabourMode, Price, PriceProfile};
+use crate::settings::{self, Versioned};
+
+use chrono::prelude::Local;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+const FORMAT: &str = "plise-fiyat-listesi";
+
+/// Who publishes price lists from this shop and the key shared between the shops
+/// to sign them. `file` is taken relative to the data folder.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceListSettings {
+    pub publisher: String,
+    pub key: String,
+    pub file: String,
+}
+
+impl Default for PriceListSettings {
+    fn default() -> Self {
+        PriceListSettings {
+            publisher: "Merkez".to_string(),
+            key: "".to_string(),
+            file: "fiyat-listesi.json".to_string(),
+        }
+    }
+}
+
+impl Versioned for PriceListSettings {
+    const FILE_NAME: &'static str = "price_list.json";
+    const VERSION: u32 = 1;
+}
+
+/// A price list file as published by head office. `sha256` catches files damaged on
+/// the way, `signature` (HMAC-SHA256 with the shared key) catches edited ones.
+#[derive(Serialize, Deserialize)]
+struct PriceListFile {
+    format: String,
+    published_at: String,
+    publisher: String,
+    price: Value,
+    sha256: String,
+    signature: String,
+}
+
+/// The signed part of the file. `Value` keeps its keys sorted, so the text is the
+/// same when written and when read back.
+fn payload(published_at: &str, publisher: &str, price: &Value) -> String {
+    json!({
+        "published_at": published_at,
+        "publisher": publisher,
+        "price": price,
+    })
+    .to_string()
+}
+
+fn mac(key: &str, payload: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// A price list that passed its checks.
+pub struct PriceList {
+    pub published_at: String,
+    pub publisher: String,
+    pub price: Price,
+}
+
+/// What taking a change does to the prices.
+pub enum ChangeKind {
+    /// A component, rate or measure, by its key in [`Price::set`].
+    Value {
+        key: &'static str,
+        old: f32,
+        new: f32,
+    },
+    LabourMode(LabourMode),
+    /// Puts the profile in place of the one with this name, or removes that one if `None`.
+    Profile(String, Option<PriceProfile>),
+    /// Puts the accessory in place of the one with this name, or removes that one if `None`.
+    Accessory(String, Option<Accessory>),
+}
+
+/// One difference between the current prices and an imported list.
+pub struct PriceChange {
+    pub label: String,
+    pub old: String,
+    pub new: String,
+    pub kind: ChangeKind,
+    pub accept: bool,
+}
+
+impl PriceChange {
+    fn new(label: String, old: String, new: String, kind: ChangeKind) -> Self {
+        PriceChange {
+            label,
+            old,
+            new,
+            kind,
+            accept: true,
+        }
+    }
+
+    /// The change of a value in percent, or `None` for other changes and values that were 0.
+    pub fn percent(&self) -> Option<f32> {
+        match self.kind {
+            ChangeKind::Value { old, new, .. } if old != 0. => Some((new - old) / old * 100.),
+            _ => None,
+        }
+    }
+}
+
+/// The lists in `Price` whose entries are told apart by name.
+trait Named: Clone + Serialize {
+    const KIND: &'static str;
+
+    fn name(&self) -> &str;
+
+    /// Everything about the entry, for comparing the old and the new one side by side.
+    fn describe(&self) -> String;
+
+    fn change(name: String, entry: Option<Self>) -> ChangeKind;
+}
+
+impl Named for PriceProfile {
+    const KIND: &'static str = "Fiyat Profili";
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn describe(&self) -> String {
+        let mut text = format!(
+            "Kar %{} / %{} / %{}",
+            self.klasik_kar, self.genis_kar, self.ince_kar
+        );
+        for (key, value) in &self.overrides {
+            text += &format!(", {} {:.2}", key, value);
+        }
+        text
+    }
+
+    fn change(name: String, entry: Option<Self>) -> ChangeKind {
+        ChangeKind::Profile(name, entry)
+    }
+}
+
+impl Named for Accessory {
+    const KIND: &'static str = "Aksesuar";
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn describe(&self) -> String {
+        let series: Vec<_> = self.series.iter().map(|series| series.label()).collect();
+        let mut supplier = self.supplier.clone();
+        if !self.part_no.is_empty() {
+            supplier += &format!(" ({})", self.part_no);
+        }
+        format!(
+            "{:.2} TL/{} × {}, {}, {}, paket {}",
+            self.price,
+            if self.per_metre { "m" } else { "adet" },
+            self.quantity,
+            series.join(" "),
+            supplier,
+            self.pack_size
+        )
+    }
+
+    fn change(name: String, entry: Option<Self>) -> ChangeKind {
+        ChangeKind::Accessory(name, entry)
+    }
+}
+
+/// Entries added, edited or removed between `old` and `new`, in the order of `old`
+/// followed by the added ones.
+fn named_changes<T: Named>(old: &[T], new: &[T]) -> Vec<PriceChange> {
+    let mut names: Vec<&str> = Vec::new();
+    for entry in old.iter().chain(new) {
+        if !names.contains(&entry.name()) {
+            names.push(entry.name());
+        }
+    }
+    let find = |list: &[T], name: &str| list.iter().find(|entry| entry.name() == name).cloned();
+    let describe = |entry: &Option<T>| entry.as_ref().map_or("Yok".to_string(), T::describe);
+    let value = |entry: &Option<T>| serde_json::to_value(entry).ok();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (before, after) = (find(old, name), find(new, name));
+            (value(&before) != value(&after)).then(|| {
+                PriceChange::new(
+                    format!("{}: {}", T::KIND, name),
+                    describe(&before),
+                    describe(&after),
+                    T::change(name.to_string(), after),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Puts `entry` in place of the one named `name`, adding it if there is none, or
+/// removes that one if `entry` is `None`.
+fn replace_named<T: Named>(list: &mut Vec<T>, name: &str, entry: &Option<T>) {
+    let position = list.iter().position(|current| current.name() == name);
+    match (position, entry) {
+        (Some(i), Some(entry)) => list[i] = entry.clone(),
+        (None, Some(entry)) => list.push(entry.clone()),
+        (Some(i), None) => {
+            list.remove(i);
+        }
+        (None, None) => {}
+    }
+}
+
+impl PriceListSettings {
+    pub fn create_from_file() -> Result<Self, AppError> {
+        settings::load(Self::FILE_NAME)
+    }
+
+    pub fn to_file(&self) -> Result<(), AppError> {
+        settings::save(Self::FILE_NAME, self)
+    }
+
+    pub fn path(&self) -> PathBuf {
+        settings::data_dir().join(&self.file)
+    }
+
+    pub fn export(&self, price: &Price) -> Result<(), AppError> {
+        let path = self.path();
+        if self.key.is_empty() {
+            return Err(AppError::PriceList {
+                path,
+                reason: "paylaşım anahtarı girilmemiş",
+            });
+        }
+        let published_at = Local::now().to_rfc3339();
+        let price = settings::to_versioned_value(price)?;
+        let payload = payload(&published_at, &self.publisher, &price);
+        let file = PriceListFile {
+            format: FORMAT.to_string(),
+            sha256: hex::encode(Sha256::digest(payload.as_bytes())),
+            signature: hex::encode(mac(&self.key, &payload).finalize().into_bytes()),
+            published_at,
+            publisher: self.publisher.clone(),
+            price,
+        };
+        output::write_file(&path, serde_json::to_string_pretty(&file)?.as_bytes())
+    }
+
+    /// Reads and checks a published price list. Lists written with an older price
+    /// format are migrated like `prices.json`.
+    pub fn import(&self) -> Result<PriceList, AppError> {
+        let path = self.path();
+        let rejected = |reason| AppError::PriceList {
+            path: path.clone(),
+            reason,
+        };
+        if self.key.is_empty() {
+            return Err(rejected("paylaşım anahtarı girilmemiş"));
+        }
+        let buffer = fs::read_to_string(&path).map_err(|source| AppError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let file: PriceListFile =
+            serde_json::from_str(&buffer).map_err(|source| AppError::Parse {
+                path: path.clone(),
+                source,
+            })?;
+        if file.format != FORMAT {
+            return Err(rejected("bu dosya bir fiyat listesi değil"));
+        }
+
+        let payload = payload(&file.published_at, &file.publisher, &file.price);
+        if hex::encode(Sha256::digest(payload.as_bytes())) != file.sha256 {
+            return Err(rejected("dosya bozulmuş veya değiştirilmiş"));
+        }
+        let signature = hex::decode(&file.signature).map_err(|_| rejected("imza okunamadı"))?;
+        mac(&self.key, &payload)
+            .verify_slice(&signature)
+            .map_err(|_| rejected("imza geçersiz, anahtar farklı veya dosya değiştirilmiş"))?;
+
+        Ok(PriceList {
+            published_at: file.published_at,
+            publisher: file.publisher,
+            price: settings::upgrade(&path, file.price)?,
+        })
+    }
+}
+
+impl PriceList {
+    pub fn changes(&self, current: &Price) -> Vec<PriceChange> {
+        let values = |price: &Price| {
+            let mut values = price.components();
+            values.extend(price.rates());
+            values.extend(price.measures());
+            values
+        };
+        let mut changes: Vec<PriceChange> = values(current)
+            .into_iter()
+            .zip(values(&self.price))
+            .filter(|(old, new)| old.value != new.value)
+            .map(|(old, new)| {
+                PriceChange::new(
+                    old.label.to_string(),
+                    format!("{:.2} {}", old.value, old.unit),
+                    format!("{:.2} {}", new.value, new.unit),
+                    ChangeKind::Value {
+                        key: old.key,
+                        old: old.value,
+                        new: new.value,
+                    },
+                )
+            })
+            .collect();
+        let (old_mode, new_mode) = (current.labour.mode, self.price.labour.mode);
+        if old_mode != new_mode {
+            changes.push(PriceChange::new(
+                "İşçilik Hesabı".to_string(),
+                old_mode.label().to_string(),
+                new_mode.label().to_string(),
+                ChangeKind::LabourMode(new_mode),
+            ));
+        }
+        changes.extend(named_changes(&current.profiles, &self.price.profiles));
+        changes.extend(named_changes(&current.accessories, &self.price.accessories));
+        changes
+    }
+}
+
+/// Copies the accepted changes into `price`.
+pub fn apply(price: &mut Price, changes: &[PriceChange]) {
+    for change in changes.iter().filter(|change| change.accept) {
+        match &change.kind {
+            ChangeKind::Value { key, new, .. } => price.set(key, *new),
+            ChangeKind::LabourMode(mode) => price.labour.mode = *mode,
+            ChangeKind::Profile(name, profile) => replace_named(&mut price.profiles, name, profile),
+            ChangeKind::Accessory(name, accessory) => {
+                replace_named(&mut price.accessories, name, accessory)
+            }
+        }
+    }
+}
+
+/// An imported list waiting for the user to pick which changes to take.
+pub struct PriceImport {
+    pub list: PriceList,
+    pub changes: Vec<PriceChange>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plise::PliseName;
+
+    fn same(left: &Price, right: &Price) -> bool {
+        serde_json::to_value(left).unwrap() == serde_json::to_value(right).unwrap()
+    }
+
+    /// Publishes `published` to its own file and imports it back.
+    fn publish(file: &str, published: &Price) -> PriceList {
+        settings::init_test_data_dir();
+        let settings = PriceListSettings {
+            key: "ortak-anahtar".to_string(),
+            file: file.to_string(),
+            ..PriceListSettings::default()
+        };
+        settings.export(published).unwrap();
+        settings.import().unwrap()
+    }
+
+    /// Applies every change of `published` to `current` and checks it then matches.
+    fn round_trip(file: &str, current: &Price, published: &Price) -> Vec<PriceChange> {
+        let changes = publish(file, published).changes(current);
+        let mut applied = current.clone();
+        apply(&mut applied, &changes);
+        assert!(same(&applied, published));
+        assert!(publish(file, published).changes(&applied).is_empty());
+        changes
+    }
+
+    fn labels(changes: &[PriceChange]) -> Vec<&str> {
+        changes.iter().map(|change| change.label.as_str()).collect()
+    }
+
+    #[test]
+    fn unchanged_prices_have_no_changes() {
+        let price = Price::default();
+        assert!(publish("ayni.json", &price).changes(&price).is_empty());
+    }
+
+    #[test]
+    fn components_and_rates_round_trip() {
+        let current = Price::default();
+        let mut published = current.clone();
+        published.color_price.beyaz = 99.;
+        published.tul.polen.price = 65.5;
+        published.klasik_kar = 35.;
+        published.labour.hourly_rate = 200.;
+
+        let changes = round_trip("fiyatlar.json", &current, &published);
+        assert_eq!(
+            labels(&changes),
+            [
+                "Beyaz Profil",
+                "Polen Tülü",
+                "Klasik Seri Kar",
+                "Saat Ücreti"
+            ]
+        );
+        assert_eq!(changes[0].new, "99.00 TL/m");
+        assert_eq!(changes[2].percent(), Some(75.));
+    }
+
+    #[test]
+    fn assembly_times_and_labour_mode_round_trip() {
+        let current = Price::default();
+        let mut published = current.clone();
+        published.labour.mode = LabourMode::Both;
+        published.labour.genis.fixed_minutes = 25.;
+        published.labour.ince.minutes_per_metre = 3.5;
+        published.labour.klasik.minutes_per_piece = 0.75;
+
+        let changes = round_trip("montaj.json", &current, &published);
+        assert_eq!(
+            labels(&changes),
+            [
+                "Klasik Parça Başına",
+                "Geniş Sabit Süre",
+                "İnce Profil Metresi",
+                "İşçilik Hesabı"
+            ]
+        );
+        assert_eq!(changes[3].old, "Yüzde");
+        assert_eq!(changes[3].new, "İkisi Birden");
+        assert_eq!(changes[3].percent(), None);
+    }
+
+    #[test]
+    fn roll_widths_round_trip() {
+        let current = Price::default();
+        let mut published = current.clone();
+        published.tul.standart.roll_width = 280.;
+        published.tul.karartma.roll_width = 200.;
+
+        let changes = round_trip("rulo.json", &current, &published);
+        assert_eq!(
+            labels(&changes),
+            ["Standart Tül Rulo Eni", "Karartma Kumaşı Rulo Eni"]
+        );
+        assert_eq!(changes[0].old, "250.00 cm");
+    }
+
+    #[test]
+    fn price_profiles_round_trip() {
+        let current = Price::default();
+        let mut published = current.clone();
+        published.profiles[1].klasik_kar = 12.;
+        published.profiles[1]
+            .overrides
+            .insert("beyaz".to_string(), 40.);
+        published.profiles.remove(2);
+        published.profiles.push(PriceProfile {
+            name: "Kurumsal".to_string(),
+            ..PriceProfile::default()
+        });
+
+        let changes = round_trip("profiller.json", &current, &published);
+        assert_eq!(
+            labels(&changes),
+            [
+                "Fiyat Profili: Bayi",
+                "Fiyat Profili: Müteahhit",
+                "Fiyat Profili: Kurumsal"
+            ]
+        );
+        assert_eq!(changes[0].new, "Kar %12 / %10 / %10, beyaz 40.00");
+        assert_eq!(changes[1].new, "Yok");
+        assert_eq!(changes[2].old, "Yok");
+    }
+
+    #[test]
+    fn accessories_round_trip() {
+        let current = Price::default();
+        let mut published = current.clone();
+        published.accessories[0].price = 40.;
+        published.accessories[2].part_no = "FC-7".to_string();
+        published.accessories.remove(3);
+        published.accessories.push(Accessory {
+            name: "Çocuk Kilidi".to_string(),
+            series: vec![PliseName::Genis],
+            price: 20.,
+            ..Accessory::default()
+        });
+
+        let changes = round_trip("aksesuarlar.json", &current, &published);
+        assert_eq!(
+            labels(&changes),
+            [
+                "Aksesuar: Uzun Kulp",
+                "Aksesuar: Fırça Conta",
+                "Aksesuar: Siyah İp",
+                "Aksesuar: Çocuk Kilidi"
+            ]
+        );
+        assert_eq!(
+            changes[1].new,
+            "8.00 TL/m × 2, Klasik Genis Ince, Aksesuar Tedarikçisi (FC-7), paket 1"
+        );
+        assert_eq!(
+            changes[3].new,
+            "20.00 TL/adet × 1, Genis, Aksesuar Tedarikçisi, paket 1"
+        );
+    }
+
+    #[test]
+    fn only_ticked_changes_are_applied() {
+        let current = Price::default();
+        let mut published = current.clone();
+        published.kdv = 10.;
+        published.labour.mode = LabourMode::Time;
+        published.accessories.clear();
+
+        let mut changes = publish("secim.json", &published).changes(&current);
+        assert_eq!(changes.len(), 2 + current.accessories.len());
+        changes[0].accept = false;
+        changes[2].accept = false;
+        let mut applied = current.clone();
+        apply(&mut applied, &changes);
+
+        assert_eq!(applied.kdv, current.kdv);
+        assert!(applied.labour.mode == LabourMode::Time);
+        let names: Vec<_> = applied
+            .accessories
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect();
+        assert_eq!(names, [current.accessories[0].name.as_str()]);
+    }
+
+    #[test]
+    fn edited_lists_are_rejected() {
+        publish("degisik.json", &Price::default());
+        let settings = PriceListSettings {
+            key: "ortak-anahtar".to_string(),
+            file: "degisik.json".to_string(),
+            ..PriceListSettings::default()
+        };
+        let text = fs::read_to_string(settings.path()).unwrap();
+        let edited = text.replace("\"price\": 35.0", "\"price\": 1.0");
+        assert_ne!(text, edited);
+        fs::write(settings.path(), edited).unwrap();
+        assert!(matches!(settings.import(), Err(AppError::PriceList { .. })));
+    }
+}
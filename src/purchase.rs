@@ -0,0 +1,666 @@
+use crate::bom;
+use crate::consumable::Consumable;
+use crate::error::AppError;
+use crate::price::Price;
+use crate::settings::{self, Versioned};
+use crate::store::StockLevel;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// One supplier's part for a price component.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Source {
+    pub supplier: String,
+    pub part_no: String,
+    /// In the unit of the component: TL/m, TL/m² or TL/adet.
+    pub price: f32,
+    /// Bar length for profiles, roll size for the mesh and şerit, pieces per pack otherwise.
+    pub pack_size: f32,
+    /// The smallest quantity the supplier sells, in the same unit.
+    pub min_quantity: f32,
+    pub lead_days: u32,
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source {
+            supplier: "".to_string(),
+            part_no: "".to_string(),
+            price: 0.,
+            pack_size: 1.,
+            min_quantity: 0.,
+            lead_days: 0,
+        }
+    }
+}
+
+impl Source {
+    /// Sources without a pack size are sold by the unit.
+    fn pack(&self) -> f32 {
+        if self.pack_size > 0. {
+            self.pack_size
+        } else {
+            1.
+        }
+    }
+
+    /// Packs to buy for `needed`, the minimum included.
+    fn packs(&self, needed: f32) -> u32 {
+        // Allows for rounding errors, so 12.000001 m in 6 m bars is still two bars.
+        (needed.max(self.min_quantity) / self.pack() - 0.0001)
+            .ceil()
+            .max(1.) as u32
+    }
+
+    /// What buying `needed` costs, in whole packs.
+    fn cost(&self, needed: f32) -> f32 {
+        self.packs(needed) as f32 * self.pack() * self.price
+    }
+}
+
+/// Where a component of [`Price::components`] can be bought.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Part {
+    pub component: String,
+    pub sources: Vec<Source>,
+    /// The source costing and purchase orders use. Its price is the one in `Price`.
+    pub preferred: usize,
+}
+
+impl Part {
+    pub fn preferred(&self) -> Option<&Source> {
+        self.sources.get(self.preferred)
+    }
+}
+
+/// Default supplier and pack size of each component, as (component, supplier, pack size).
+const DEFAULT_SOURCES: [(&str, &str, f32); 17] = [
+    ("beyaz", "Profil Tedarikçisi", 6.),
+    ("boya", "Profil Tedarikçisi", 6.),
+    ("ahsap", "Profil Tedarikçisi", 6.),
+    ("ozel", "Profil Tedarikçisi", 6.),
+    ("tul", "Tül Tedarikçisi", 36.),
+    ("evcil_tul", "Tül Tedarikçisi", 36.),
+    ("polen_tul", "Tül Tedarikçisi", 36.),
+    ("karartma_tul", "Tül Tedarikçisi", 30.),
+    ("serit", "Aksesuar Tedarikçisi", 100.),
+    ("teker", "Aksesuar Tedarikçisi", 100.),
+    ("klips", "Aksesuar Tedarikçisi", 100.),
+    ("stop", "Aksesuar Tedarikçisi", 100.),
+    ("donus", "Aksesuar Tedarikçisi", 50.),
+    ("ince_kose", "Aksesuar Tedarikçisi", 100.),
+    ("klasik_kose", "Aksesuar Tedarikçisi", 100.),
+    ("genis_kose", "Aksesuar Tedarikçisi", 100.),
+    ("adaptor", "Profil Tedarikçisi", 6.),
+];
+
+/// The supplier catalogue, kept in `purchase.json`.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PurchaseSettings {
+    pub parts: Vec<Part>,
+}
+
+impl Default for PurchaseSettings {
+    fn default() -> Self {
+        PurchaseSettings {
+            parts: DEFAULT_SOURCES
+                .iter()
+                .map(|(component, supplier, pack_size)| Part {
+                    component: component.to_string(),
+                    sources: vec![Source {
+                        supplier: supplier.to_string(),
+                        pack_size: *pack_size,
+                        ..Source::default()
+                    }],
+                    preferred: 0,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `purchase.json` history:
+/// - 1: one supplier and pack size per BOM item.
+/// - 2: one or more sources per price component, with prices, minimums and lead times.
+impl Versioned for PurchaseSettings {
+    const FILE_NAME: &'static str = "purchase.json";
+    const VERSION: u32 = 2;
+
+    fn migrate(version: u32, value: Value) -> Value {
+        match version {
+            1 => {
+                let materials = value["materials"].as_array().cloned().unwrap_or_default();
+                let parts: Vec<Value> = DEFAULT_SOURCES
+                    .iter()
+                    .map(|(component, supplier, pack_size)| {
+                        let item = match *component {
+                            "beyaz" | "boya" | "ahsap" | "ozel" => "kasa",
+                            "ince_kose" | "klasik_kose" | "genis_kose" => "kose",
+                            item => item,
+                        };
+                        let material = materials.iter().find(|m| m["item"] == item);
+                        let field = |name: &str, default: Value| {
+                            material.map_or(default, |m| m[name].clone())
+                        };
+                        json!({
+                            "component": component,
+                            "sources": [{
+                                "supplier": field("supplier", json!(supplier)),
+                                "pack_size": field("pack_size", json!(pack_size)),
+                            }],
+                            "preferred": 0,
+                        })
+                    })
+                    .collect();
+                json!({ "parts": parts })
+            }
+            _ => value,
+        }
+    }
+}
+
+impl PurchaseSettings {
+    pub fn create_from_file() -> Result<Self, AppError> {
+        settings::load(Self::FILE_NAME)
+    }
+
+    pub fn to_file(&self) -> Result<(), AppError> {
+        settings::save(Self::FILE_NAME, self)
+    }
+
+    pub fn part(&self, component: &str) -> Option<&Part> {
+        self.parts.iter().find(|part| part.component == component)
+    }
+
+    /// Copies the prices in `Price` to the preferred sources, adding a part for any
+    /// component the catalogue does not know yet, with its default source if it has one.
+    pub fn sync_prices(&mut self, price: &Price) {
+        for component in price.components() {
+            let index = match self.parts.iter().position(|p| p.component == component.key) {
+                Some(index) => index,
+                None => {
+                    let part = PurchaseSettings::default()
+                        .parts
+                        .into_iter()
+                        .find(|p| p.component == component.key)
+                        .unwrap_or(Part {
+                            component: component.key.to_string(),
+                            sources: Vec::new(),
+                            preferred: 0,
+                        });
+                    self.parts.push(part);
+                    self.parts.len() - 1
+                }
+            };
+            let part = &mut self.parts[index];
+            if part.sources.is_empty() {
+                part.sources.push(Source::default());
+            }
+            part.preferred = part.preferred.min(part.sources.len() - 1);
+            part.sources[part.preferred].price = component.value;
+        }
+    }
+
+    /// Costs with the prices of the preferred sources from now on.
+    pub fn apply_prices(&self, price: &mut Price) {
+        for part in &self.parts {
+            if let Some(source) = part.preferred() {
+                price.set(&part.component, source.price);
+            }
+        }
+    }
+}
+
+const ITEM_LABELS: [(&str, &str); 13] = [
+    ("kasa", "Kasa Profili"),
+    ("kanat", "Kanat Profili"),
+    ("tul", "Standart Tül"),
+    ("evcil_tul", "Evcil Hayvan Tülü"),
+    ("polen_tul", "Polen Tülü"),
+    ("karartma_tul", "Karartma Kumaşı"),
+    ("serit", "Şerit"),
+    ("kose", "Köşe"),
+    ("teker", "Teker"),
+    ("klips", "Klips"),
+    ("stop", "Stop"),
+    ("donus", "Dönüş"),
+    ("adaptor", "Adaptör Profili"),
+];
+
+/// Materials are bought in metres, square metres and pieces rather than the centimetres
+/// of the BOM.
+fn purchase_unit(bom_unit: &str) -> (&'static str, f32) {
+    match bom_unit {
+        "cm" => ("m", 100.),
+        "cm2" => ("m²", 10000.),
+        _ => ("adet", 1.),
+    }
+}
+
+/// The total need for one material over the chosen orders, in the unit it is bought in.
+pub struct Requirement {
+    pub item: String,
+    pub series: Option<&'static str>,
+    pub color: Option<String>,
+    /// The price component it is bought as, [`ACCESSORY`] for add-ons.
+    pub component: &'static str,
+    pub quantity: f32,
+    pub unit: &'static str,
+    /// Where an add-on is bought, from its settings rather than the catalogue.
+    pub source: Option<Source>,
+}
+
+/// The component of add-on requirements, which are not in the catalogue.
+pub const ACCESSORY: &str = "aksesuar";
+
+impl Requirement {
+    /// e.g. "Kasa Profili Klasik Beyaz" or "Kasa Profili Klasik Özel RAL RAL 7016".
+    pub fn name(&self) -> String {
+        let label = ITEM_LABELS
+            .iter()
+            .find(|(item, _)| *item == self.item)
+            .map_or(self.item.as_str(), |(_, label)| label);
+        [Some(label), self.series, self.color.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The stock kept for this material, if any.
+    pub fn stock<'a>(&self, stock: &'a [StockLevel]) -> Option<&'a StockLevel> {
+        stock.iter().find(|level| {
+            level.item == self.item
+                && level.series == self.series.unwrap_or("")
+                && level.color == self.color.as_deref().unwrap_or("")
+        })
+    }
+
+    pub fn stock_level(&self, quantity: f32) -> StockLevel {
+        StockLevel {
+            item: self.item.clone(),
+            series: self.series.unwrap_or("").to_string(),
+            color: self.color.clone().unwrap_or_default(),
+            quantity,
+        }
+    }
+}
+
+/// Adds up the BOM of every pleat of the orders, add-ons included. Profiles are needed
+/// in the lengths the cut list cuts, not the weighted lengths the costing uses.
+pub fn requirements(orders: &[&[Consumable]], price: &Price) -> Vec<Requirement> {
+    let mut requirements: Vec<Requirement> = Vec::new();
+    for consumable in orders.iter().flat_map(|lines| lines.iter()) {
+        let items = bom::items(consumable)
+            .into_iter()
+            .map(|item| (Price::bom_component(&item.item, consumable), None, item));
+        let accessories = price.accessories_of(consumable).map(|accessory| {
+            let source = Source {
+                supplier: accessory.supplier.clone(),
+                part_no: accessory.part_no.clone(),
+                price: accessory.price,
+                pack_size: accessory.pack_size,
+                ..Source::default()
+            };
+            let item = bom::accessory_item(accessory, consumable);
+            (Some(ACCESSORY), Some(source), item)
+        });
+        for (component, source, item) in items.chain(accessories) {
+            let Some(component) = component else {
+                continue;
+            };
+            let (unit, per_unit) = purchase_unit(item.unit);
+            let quantity = item.quantity / per_unit;
+            match requirements
+                .iter_mut()
+                .find(|r| r.item == item.item && r.series == item.series && r.color == item.color)
+            {
+                Some(requirement) => requirement.quantity += quantity,
+                None => requirements.push(Requirement {
+                    item: item.item,
+                    series: item.series,
+                    color: item.color,
+                    component,
+                    quantity,
+                    unit,
+                    source,
+                }),
+            }
+        }
+    }
+    requirements.retain(|requirement| requirement.quantity > 0.);
+    requirements
+}
+
+#[derive(Serialize)]
+pub struct PurchaseLine {
+    pub name: String,
+    pub part_no: String,
+    /// What is missing after the stock is used.
+    pub needed: f32,
+    pub packs: u32,
+    pub pack_size: f32,
+    pub quantity: f32,
+    pub unit: &'static str,
+    pub unit_price: f32,
+    pub total: f32,
+}
+
+#[derive(Serialize)]
+pub struct PurchaseOrder {
+    pub supplier: String,
+    pub lead_days: u32,
+    pub lines: Vec<PurchaseLine>,
+    pub total: f32,
+}
+
+/// What to order from each preferred supplier: the requirements less the stock, rounded
+/// up to whole bars, rolls and packs and to the supplier's minimum.
+pub fn purchase_orders(
+    settings: &PurchaseSettings,
+    requirements: &[Requirement],
+    stock: &[StockLevel],
+) -> Vec<PurchaseOrder> {
+    let mut orders: Vec<PurchaseOrder> = Vec::new();
+    for requirement in requirements {
+        let in_stock = requirement.stock(stock).map_or(0., |level| level.quantity);
+        let needed = requirement.quantity - in_stock.max(0.);
+        if needed <= 0. {
+            continue;
+        }
+        let default_source = Source::default();
+        let source = requirement
+            .source
+            .as_ref()
+            .or_else(|| {
+                settings
+                    .part(requirement.component)
+                    .and_then(Part::preferred)
+            })
+            .unwrap_or(&default_source);
+        let packs = source.packs(needed);
+        let pack_size = source.pack();
+        let quantity = packs as f32 * pack_size;
+        let line = PurchaseLine {
+            name: requirement.name(),
+            part_no: source.part_no.clone(),
+            needed,
+            packs,
+            pack_size,
+            quantity,
+            unit: requirement.unit,
+            unit_price: source.price,
+            total: quantity * source.price,
+        };
+        match orders
+            .iter_mut()
+            .find(|order| order.supplier == source.supplier)
+        {
+            Some(order) => {
+                order.total += line.total;
+                order.lead_days = order.lead_days.max(source.lead_days);
+                order.lines.push(line);
+            }
+            None => orders.push(PurchaseOrder {
+                supplier: source.supplier.clone(),
+                lead_days: source.lead_days,
+                total: line.total,
+                lines: vec![line],
+            }),
+        }
+    }
+    orders
+}
+
+#[derive(Serialize)]
+pub struct SourceComparison {
+    pub supplier: String,
+    pub part_no: String,
+    pub price: f32,
+    /// Price difference to the preferred source, in percent.
+    pub difference: f32,
+    pub pack_size: f32,
+    pub min_quantity: f32,
+    pub lead_days: u32,
+    /// What the chosen orders' need of the component would cost from this source,
+    /// whole packs and minimums included.
+    pub order_total: Option<f32>,
+    pub preferred: bool,
+    pub cheapest: bool,
+}
+
+#[derive(Serialize)]
+pub struct PartComparison {
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub needed: f32,
+    pub sources: Vec<SourceComparison>,
+}
+
+/// Every component with more than one source, its sources side by side.
+pub fn compare(
+    settings: &PurchaseSettings,
+    price: &Price,
+    requirements: &[Requirement],
+) -> Vec<PartComparison> {
+    price
+        .components()
+        .into_iter()
+        .filter_map(|component| {
+            let part = settings.part(component.key)?;
+            if part.sources.len() < 2 {
+                return None;
+            }
+            let needed: Vec<f32> = requirements
+                .iter()
+                .filter(|r| r.component == component.key)
+                .map(|r| r.quantity)
+                .collect();
+            let order_total = |source: &Source| {
+                (!needed.is_empty()).then(|| {
+                    needed
+                        .iter()
+                        .map(|needed| source.cost(*needed))
+                        .sum::<f32>()
+                })
+            };
+            let preferred_price = part.preferred().map_or(0., |source| source.price);
+            let cheapest = part
+                .sources
+                .iter()
+                .map(|source| order_total(source).unwrap_or(source.price))
+                .fold(f32::INFINITY, f32::min);
+            Some(PartComparison {
+                label: component.label,
+                unit: component.unit.trim_start_matches("TL/"),
+                needed: needed.iter().sum(),
+                sources: part
+                    .sources
+                    .iter()
+                    .enumerate()
+                    .map(|(i, source)| SourceComparison {
+                        supplier: source.supplier.clone(),
+                        part_no: source.part_no.clone(),
+                        price: source.price,
+                        difference: if preferred_price > 0. {
+                            (source.price - preferred_price) / preferred_price * 100.
+                        } else {
+                            0.
+                        },
+                        pack_size: source.pack_size,
+                        min_quantity: source.min_quantity,
+                        lead_days: source.lead_days,
+                        order_total: order_total(source),
+                        preferred: i == part.preferred,
+                        cheapest: order_total(source).unwrap_or(source.price) <= cheapest,
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plise::ColorName;
+    use std::path::Path;
+
+    #[test]
+    fn migrates_v1_materials_to_sources() {
+        let value = serde_json::from_str(include_str!("fixtures/purchase.v1.json")).unwrap();
+        let settings: PurchaseSettings =
+            settings::upgrade(Path::new(PurchaseSettings::FILE_NAME), value).unwrap();
+        assert_eq!(settings.parts.len(), DEFAULT_SOURCES.len());
+
+        let source = |component: &str| settings.part(component).unwrap().preferred().unwrap();
+        // Every profile colour is bought from the old kasa supplier.
+        for component in ["beyaz", "boya", "ahsap", "ozel"] {
+            assert_eq!(source(component).supplier, "Alüminyum A.Ş.");
+            assert_eq!(source(component).pack_size, 6.5);
+        }
+        for component in ["ince_kose", "klasik_kose", "genis_kose"] {
+            assert_eq!(source(component).supplier, "Köşeci");
+            assert_eq!(source(component).pack_size, 250.);
+        }
+        assert_eq!(source("tul").supplier, "Tülcü");
+        assert_eq!(source("tul").pack_size, 50.);
+        // Items missing from the file keep their defaults.
+        assert_eq!(source("donus").supplier, "Aksesuar Tedarikçisi");
+        assert_eq!(source("donus").pack_size, 50.);
+        assert_eq!(source("evcil_tul").supplier, "Tül Tedarikçisi");
+        assert_eq!(source("teker").price, 0.);
+        assert_eq!(source("teker").lead_days, 0);
+    }
+
+    #[test]
+    fn profiles_are_bought_in_cut_lengths() {
+        let order = vec![Consumable::default(); 10];
+        let requirements = requirements(&[&order], &Price::default());
+        let requirement = |item: &str| requirements.iter().find(|r| r.item == item).unwrap();
+        // 4 x 37.5 cm of kasa and 32 cm of kanat per 40 x 40 pleat.
+        assert!((requirement("kasa").quantity - 15.).abs() < 0.001);
+        assert!((requirement("kanat").quantity - 3.2).abs() < 0.001);
+        assert_eq!(requirement("kasa").unit, "m");
+
+        let orders = purchase_orders(&PurchaseSettings::default(), &requirements, &[]);
+        let line = |name: &str| {
+            orders
+                .iter()
+                .flat_map(|order| &order.lines)
+                .find(|line| line.name == name)
+                .unwrap()
+        };
+        assert_eq!(line("Kasa Profili Klasik Beyaz").packs, 3);
+        assert_eq!(line("Kanat Profili Klasik Beyaz").packs, 1);
+    }
+
+    #[test]
+    fn accessories_are_ordered_from_their_supplier() {
+        let mut price = Price::default();
+        price.accessories[0].supplier = "Kulpçu".to_string();
+        price.accessories[0].part_no = "UK-1".to_string();
+        price.accessories[0].pack_size = 10.;
+        let mut order = vec![Consumable::default(); 3];
+        for consumable in &mut order {
+            consumable.aksesuarlar = vec!["Uzun Kulp".to_string(), "Fırça Conta".to_string()];
+        }
+
+        let requirements = requirements(&[&order], &price);
+        let kulp = requirements.iter().find(|r| r.item == "Uzun Kulp").unwrap();
+        assert_eq!(
+            (kulp.quantity, kulp.unit, kulp.component),
+            (3., "adet", ACCESSORY)
+        );
+        // Two lengths of the 40 cm height on each pleat.
+        let conta = requirements
+            .iter()
+            .find(|r| r.item == "Fırça Conta")
+            .unwrap();
+        assert!((conta.quantity - 2.4).abs() < 0.001);
+        assert_eq!(conta.unit, "m");
+
+        let orders = purchase_orders(&PurchaseSettings::default(), &requirements, &[]);
+        let kulpcu = orders.iter().find(|o| o.supplier == "Kulpçu").unwrap();
+        let line = &kulpcu.lines[0];
+        assert_eq!(
+            (line.name.as_str(), line.part_no.as_str()),
+            ("Uzun Kulp", "UK-1")
+        );
+        assert_eq!((line.packs, line.quantity, line.unit_price), (1, 10., 35.));
+        let conta = orders
+            .iter()
+            .find(|o| o.supplier == "Aksesuar Tedarikçisi")
+            .and_then(|o| o.lines.iter().find(|line| line.name == "Fırça Conta"))
+            .unwrap();
+        assert_eq!(conta.unit_price, 8.);
+    }
+
+    #[test]
+    fn custom_colours_are_bought_and_stocked_per_code() {
+        let custom = |color: ColorName, code: &str| {
+            let mut consumable = Consumable {
+                renk_kodu: code.to_string(),
+                ..Consumable::default()
+            };
+            consumable.set_plise_color(color);
+            consumable
+        };
+        let order = vec![
+            custom(ColorName::Ozel, "RAL 7016"),
+            custom(ColorName::Ozel, "ral  7016"),
+            custom(ColorName::Ozel, "RAL 9005"),
+            custom(ColorName::Ahsap, "Altın Meşe"),
+            custom(ColorName::Beyaz, "RAL 9016"),
+        ];
+        let requirements = requirements(&[&order], &Price::default());
+        let kasa: Vec<_> = requirements
+            .iter()
+            .filter(|r| r.item == "kasa")
+            .map(|r| (r.name(), r.quantity))
+            .collect();
+        let names: Vec<&str> = kasa.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Kasa Profili Klasik Özel RAL RAL 7016",
+                "Kasa Profili Klasik Özel RAL RAL 9005",
+                "Kasa Profili Klasik Ahsap ALTIN MEŞE",
+                "Kasa Profili Klasik Beyaz",
+            ]
+        );
+        assert!((kasa[0].1 - 3.).abs() < 0.001);
+        assert!((kasa[1].1 - 1.5).abs() < 0.001);
+
+        // Stock of one code does not cover the other.
+        let stock = [StockLevel {
+            item: "kasa".to_string(),
+            series: "Klasik".to_string(),
+            color: "Özel RAL RAL 7016".to_string(),
+            quantity: 10.,
+        }];
+        let ral = |code: &str| {
+            requirements
+                .iter()
+                .find(|r| r.item == "kasa" && r.color.as_deref() == Some(code))
+                .unwrap()
+        };
+        assert!(ral("Özel RAL RAL 7016").stock(&stock).is_some());
+        assert!(ral("Özel RAL RAL 9005").stock(&stock).is_none());
+        assert_eq!(
+            ral("Özel RAL RAL 9005").stock_level(2.).color,
+            "Özel RAL RAL 9005"
+        );
+        let orders = purchase_orders(&PurchaseSettings::default(), &requirements, &stock);
+        let lines: Vec<&str> = orders
+            .iter()
+            .flat_map(|order| &order.lines)
+            .map(|line| line.name.as_str())
+            .filter(|name| name.starts_with("Kasa Profili Klasik Özel"))
+            .collect();
+        assert_eq!(lines, ["Kasa Profili Klasik Özel RAL RAL 9005"]);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::AppError;
+use crate::price::Price;
+use crate::settings::{self, Versioned};
+use crate::store::ProductionLine;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How days are stored in the database.
+pub const DAY_FORMAT: &str = "%Y-%m-%d";
+/// How days are shown to the user and in documents.
+pub const DISPLAY_FORMAT: &str = "%d.%m.%Y";
+
+/// Workshop capacity and the promise made to customers.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleSettings {
+    /// Assembly minutes the workshop can do in a day.
+    pub daily_minutes: f32,
+    /// Monday first.
+    pub work_days: [bool; 7],
+    /// Work days between the end of production and the promised delivery.
+    pub delivery_days: u32,
+}
+
+impl Default for ScheduleSettings {
+    fn default() -> Self {
+        ScheduleSettings {
+            daily_minutes: 480.,
+            work_days: [true, true, true, true, true, true, false],
+            delivery_days: 2,
+        }
+    }
+}
+
+impl Versioned for ScheduleSettings {
+    const FILE_NAME: &'static str = "schedule.json";
+    const VERSION: u32 = 1;
+}
+
+/// Planned assembly minutes per day.
+pub type Loads = BTreeMap<NaiveDate, f32>;
+
+pub fn parse_day(day: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(day, DAY_FORMAT).ok()
+}
+
+impl ScheduleSettings {
+    pub fn create_from_file() -> Result<Self, AppError> {
+        settings::load(Self::FILE_NAME)
+    }
+
+    pub fn to_file(&self) -> Result<(), AppError> {
+        settings::save(Self::FILE_NAME, self)
+    }
+
+    pub fn is_work_day(&self, day: NaiveDate) -> bool {
+        self.work_days[day.weekday().num_days_from_monday() as usize]
+    }
+
+    /// `day` if it is a work day, otherwise the next one.
+    fn next_work_day(&self, day: NaiveDate) -> NaiveDate {
+        if !self.work_days.contains(&true) {
+            return day;
+        }
+        let mut day = day;
+        while !self.is_work_day(day) {
+            day += Duration::days(1);
+        }
+        day
+    }
+
+    pub fn delivery_date(&self, last_day: NaiveDate) -> NaiveDate {
+        let mut day = last_day;
+        for _ in 0..self.delivery_days {
+            day = self.next_work_day(day + Duration::days(1));
+        }
+        day
+    }
+
+    /// Puts `minutes` on the first work day from `from` that has room left, or that is
+    /// still empty when a single pleat takes more than a day.
+    fn place(&self, loads: &mut Loads, minutes: f32, from: NaiveDate) -> NaiveDate {
+        let mut day = self.next_work_day(from);
+        loop {
+            let load = loads.get(&day).copied().unwrap_or(0.);
+            if load == 0. || load + minutes <= self.daily_minutes {
+                *loads.entry(day).or_default() += minutes;
+                return day;
+            }
+            day = self.next_work_day(day + Duration::days(1));
+        }
+    }
+
+    /// Plans every line from `today` on, in the order the quotes were accepted. Lines
+    /// moved by hand and lines on past days stay where they are.
+    pub fn plan(&self, price: &Price, lines: &mut [ProductionLine], today: NaiveDate) {
+        let fixed = |line: &ProductionLine| {
+            line.manual
+                || line
+                    .day
+                    .as_deref()
+                    .and_then(parse_day)
+                    .is_some_and(|day| day < today)
+        };
+        for line in lines.iter_mut().filter(|line| !fixed(line)) {
+            line.day = None;
+            line.minutes = price.assembly_minutes(&line.consumable);
+        }
+        let mut loads = loads(lines);
+        for line in lines.iter_mut().filter(|line| line.day.is_none()) {
+            let day = self.place(&mut loads, line.minutes, today);
+            line.day = Some(day.format(DAY_FORMAT).to_string());
+        }
+    }
+
+    /// The promised delivery date of every planned quote.
+    pub fn deliveries(&self, lines: &[ProductionLine]) -> BTreeMap<i64, NaiveDate> {
+        let mut last_days = BTreeMap::new();
+        for line in lines {
+            if let Some(day) = line.day.as_deref().and_then(parse_day) {
+                let last = last_days.entry(line.quote_id).or_insert(day);
+                *last = (*last).max(day);
+            }
+        }
+        last_days
+            .into_iter()
+            .map(|(quote_id, day)| (quote_id, self.delivery_date(day)))
+            .collect()
+    }
+
+    /// When an order of pleats taking `minutes` each would be delivered if it were
+    /// accepted now, after everything already planned.
+    pub fn estimate(&self, loads: &Loads, minutes: &[f32], today: NaiveDate) -> Option<NaiveDate> {
+        let mut loads = loads.clone();
+        minutes
+            .iter()
+            .map(|minutes| self.place(&mut loads, *minutes, today))
+            .max()
+            .map(|day| self.delivery_date(day))
+    }
+}
+
+pub fn loads(lines: &[ProductionLine]) -> Loads {
+    let mut loads = Loads::new();
+    for line in lines {
+        if let Some(day) = line.day.as_deref().and_then(parse_day) {
+            *loads.entry(day).or_default() += line.minutes;
+        }
+    }
+    loads
+}
+
+/// Moves the lines of a quote planned on `from` to `to`, and keeps them there.
+pub fn move_quote(lines: &mut [ProductionLine], quote_id: i64, from: NaiveDate, to: NaiveDate) {
+    let from = from.format(DAY_FORMAT).to_string();
+    for line in lines
+        .iter_mut()
+        .filter(|line| line.quote_id == quote_id && line.day.as_ref() == Some(&from))
+    {
+        line.day = Some(to.format(DAY_FORMAT).to_string());
+        line.manual = true;
+    }
+}
+
+/// The lines of one quote planned on a day.
+pub struct DayOrder<'a> {
+    pub quote_id: i64,
+    pub number: u32,
+    pub client_name: &'a str,
+    pub count: u32,
+    pub minutes: f32,
+}
+
+/// What is planned on each day, per quote, for the calendar.
+pub fn day_orders(lines: &[ProductionLine]) -> BTreeMap<NaiveDate, Vec<DayOrder<'_>>> {
+    let mut days: BTreeMap<NaiveDate, Vec<DayOrder>> = BTreeMap::new();
+    for line in lines {
+        let Some(day) = line.day.as_deref().and_then(parse_day) else {
+            continue;
+        };
+        let orders = days.entry(day).or_default();
+        match orders
+            .iter_mut()
+            .find(|order| order.quote_id == line.quote_id)
+        {
+            Some(order) => {
+                order.count += 1;
+                order.minutes += line.minutes;
+            }
+            None => orders.push(DayOrder {
+                quote_id: line.quote_id,
+                number: line.number,
+                client_name: &line.client_name,
+                count: 1,
+                minutes: line.minutes,
+            }),
+        }
+    }
+    days
+}
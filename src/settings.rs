@@ -0,0 +1,235 @@
+use crate::error::AppError;
+
+use directories::{ProjectDirs, UserDirs};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::{env, fs};
+
+/// Where settings, templates and documents are kept, chosen once at startup.
+struct DataDir {
+    path: PathBuf,
+    /// The user picked the folder (`--data-dir` or portable mode), so everything,
+    /// documents included, stays inside it.
+    explicit: bool,
+}
+
+static DATA_DIR: OnceLock<DataDir> = OnceLock::new();
+
+/// A settings type stored as JSON with a `version` field. Every change to the file
+/// format bumps `VERSION` and adds a step to `migrate`; fields added without a
+/// format change only need a default, since the types are `#[serde(default)]`.
+pub trait Versioned: Serialize + DeserializeOwned + Default {
+    /// The file's name in the data folder.
+    const FILE_NAME: &'static str;
+    const VERSION: u32;
+
+    /// Files written before the settings were versioned have no `version` field and
+    /// count as version 1 unless the type knows better.
+    fn detect_version(value: &Value) -> u32 {
+        value
+            .get("version")
+            .and_then(Value::as_u64)
+            .map_or(1, |version| version as u32)
+    }
+
+    /// Upgrades `value` from `version` to `version + 1`.
+    fn migrate(version: u32, value: Value) -> Value {
+        let _ = version;
+        value
+    }
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+/// `--data-dir <folder>` keeps the data in the given folder. `--portable`, or a file
+/// named `portable` next to the executable, keeps it next to the executable for
+/// installs on a USB stick. Otherwise the platform's per-user data folder is used.
+fn choose_data_dir(mut args: impl Iterator<Item = String>) -> DataDir {
+    let mut portable = false;
+    while let Some(arg) = args.next() {
+        if arg == "--portable" {
+            portable = true;
+        } else if let Some(path) = arg.strip_prefix("--data-dir=") {
+            return DataDir {
+                path: PathBuf::from(path),
+                explicit: true,
+            };
+        } else if arg == "--data-dir" {
+            if let Some(path) = args.next() {
+                return DataDir {
+                    path: PathBuf::from(path),
+                    explicit: true,
+                };
+            }
+        }
+    }
+
+    let exe_dir = exe_dir().unwrap_or_default();
+    if portable || exe_dir.join("portable").exists() {
+        return DataDir {
+            path: exe_dir,
+            explicit: true,
+        };
+    }
+    match ProjectDirs::from("com", "Dogus", "Plise") {
+        Some(dirs) => DataDir {
+            path: dirs.data_dir().to_path_buf(),
+            explicit: false,
+        },
+        None => DataDir {
+            path: exe_dir,
+            explicit: false,
+        },
+    }
+}
+
+/// Reads the data folder options from the command line. Must run before any settings are loaded.
+pub fn init_data_dir(args: impl Iterator<Item = String>) {
+    let _ = DATA_DIR.set(choose_data_dir(args));
+}
+
+/// Points the data folder at a fresh folder under the system temp folder, shared by
+/// all tests of the run so none of them reads the user's settings or templates.
+#[cfg(test)]
+pub fn init_test_data_dir() -> &'static Path {
+    let path = env::temp_dir().join(format!("plise-test-{}", std::process::id()));
+    let _ = DATA_DIR.set(DataDir {
+        path,
+        explicit: true,
+    });
+    let path = data_dir();
+    fs::create_dir_all(path).unwrap();
+    path
+}
+
+pub fn data_dir() -> &'static Path {
+    &DATA_DIR
+        .get_or_init(|| choose_data_dir(std::iter::empty()))
+        .path
+}
+
+/// The user's Documents folder, unless the data folder was chosen explicitly, in
+/// which case documents go into a `belgeler` folder inside it.
+pub fn default_documents_dir() -> PathBuf {
+    let data_dir = DATA_DIR.get_or_init(|| choose_data_dir(std::iter::empty()));
+    match UserDirs::new().as_ref().and_then(UserDirs::document_dir) {
+        Some(documents) if !data_dir.explicit => documents.join("Plise Belgeleri"),
+        _ => PathBuf::from("belgeler"),
+    }
+}
+
+pub fn settings_path(file_name: &str) -> PathBuf {
+    data_dir().join(file_name)
+}
+
+/// Earlier versions kept their settings next to the executable.
+fn legacy_settings_path(file_name: &str) -> Option<PathBuf> {
+    let path = exe_dir()?.join(file_name);
+    (path != settings_path(file_name) && path.exists()).then_some(path)
+}
+
+/// Reads a settings file. If it does not exist yet, a file left next to the executable
+/// by an earlier version is copied over, or else the defaults are written.
+/// Files from older versions are migrated and rewritten, with the original kept
+/// as `<name>.v<version>.bak`.
+pub fn load<T: Versioned>(file_name: &str) -> Result<T, AppError> {
+    let path = settings_path(file_name);
+    if !path.exists() {
+        match legacy_settings_path(file_name) {
+            Some(legacy) => {
+                create_data_dir()?;
+                fs::copy(&legacy, &path).map_err(|source| AppError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+            }
+            None => save(file_name, &T::default())?,
+        }
+    }
+
+    let buffer = fs::read_to_string(&path).map_err(|source| AppError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let value: Value = serde_json::from_str(&buffer).map_err(|source| AppError::Parse {
+        path: path.clone(),
+        source,
+    })?;
+
+    let original_version = T::detect_version(&value);
+    let data: T = upgrade(&path, value)?;
+    if original_version < T::VERSION {
+        let backup = path.with_file_name(format!("{}.v{}.bak", file_name, original_version));
+        fs::copy(&path, &backup).map_err(|source| AppError::Io {
+            path: backup,
+            source,
+        })?;
+        save(file_name, &data)?;
+    }
+    Ok(data)
+}
+
+/// Migrates `value`, read from `path`, to the current version of `T`.
+pub fn upgrade<T: Versioned>(path: &Path, mut value: Value) -> Result<T, AppError> {
+    let original_version = T::detect_version(&value);
+    if original_version > T::VERSION {
+        return Err(AppError::Version {
+            path: path.to_path_buf(),
+            version: original_version,
+        });
+    }
+    for version in original_version..T::VERSION {
+        value = T::migrate(version, value);
+    }
+    serde_json::from_value(value).map_err(|source| AppError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+pub fn create_data_dir() -> Result<(), AppError> {
+    fs::create_dir_all(data_dir()).map_err(|source| AppError::Io {
+        path: data_dir().to_path_buf(),
+        source,
+    })
+}
+
+/// `value` as JSON, with its `version` field.
+pub fn to_versioned_value<T: Versioned>(value: &T) -> Result<Value, AppError> {
+    // Going through the text keeps `f32` fields short: `to_value` would widen
+    // 33.3 to 33.29999923706055.
+    let mut json: Value = serde_json::from_str(&serde_json::to_string(value)?)?;
+    if let Value::Object(map) = &mut json {
+        map.insert("version".to_string(), T::VERSION.into());
+    }
+    Ok(json)
+}
+
+pub fn save<T: Versioned>(file_name: &str, value: &T) -> Result<(), AppError> {
+    create_data_dir()?;
+    let path = settings_path(file_name);
+    let json = to_versioned_value(value)?;
+    let content = serde_json::to_string(&json)?;
+    fs::write(&path, content).map_err(|source| AppError::Io { path, source })
+}
+
+/// Replaces a broken settings file with the defaults, keeping the old file as
+/// `<name>.bozuk` so nothing is lost.
+pub fn reset<T: Versioned>(file_name: &str) -> Result<T, AppError> {
+    let path = settings_path(file_name);
+    if path.exists() {
+        let backup = path.with_file_name(format!("{}.bozuk", file_name));
+        fs::rename(&path, &backup).map_err(|source| AppError::Io {
+            path: backup,
+            source,
+        })?;
+    }
+    let value = T::default();
+    save(file_name, &value)?;
+    Ok(value)
+}
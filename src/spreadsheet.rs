@@ -0,0 +1,365 @@
+use crate::consumable::Consumable;
+use crate::error::AppError;
+use crate::escape::{escape_html, sanitize_line};
+use crate::price::Price;
+
+use chrono::prelude::Utc;
+use rust_xlsxwriter::{Format, Formula, Workbook};
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::CompressionMethod;
+
+/// A spreadsheet cell. Formulas are written in A1 notation without the leading `=`
+/// and carry the value they evaluate to, so readers that do not recalculate still
+/// show the right number.
+enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+    Money(f64),
+    Formula(String, f64),
+}
+
+struct Sheet {
+    name: &'static str,
+    rows: Vec<Vec<Cell>>,
+}
+
+fn column_name(mut col: usize) -> String {
+    let mut name = String::new();
+    loop {
+        name.insert(0, (b'A' + (col % 26) as u8) as char);
+        if col < 26 {
+            return name;
+        }
+        col = col / 26 - 1;
+    }
+}
+
+fn text(s: &str) -> Cell {
+    Cell::Text(s.to_string())
+}
+
+/// Rounds to whole kuruş so the cell shows the same amount as the documents.
+fn money(value: f32) -> Cell {
+    Cell::Money((value as f64 * 100.).round() / 100.)
+}
+
+/// Builds the order and consumables sheets shared by the XLSX and ODS writers.
+fn build_sheets(
+    price: &Price,
+    consumables: &[Consumable],
+    item_count: u32,
+    client: &str,
+) -> Vec<Sheet> {
+    let lines = consumables.iter().take(item_count as usize);
+    let header = vec![
+        text("Müşteri Adı:"),
+        text(client),
+        text("Tarih:"),
+        text(&Utc::now().format("%d.%m.%Y").to_string()),
+    ];
+
+    // Order sheet: one row per pleat with its cost and price, then the totals.
+    let mut order = vec![
+        header,
+        vec![],
+        [
+            "Sıra",
+            "En (cm)",
+            "Boy (cm)",
+            "Plise Tipi",
+            "Renk Tipi",
+            "Maliyet (TL)",
+            "Fiyat (TL)",
+        ]
+        .iter()
+        .map(|s| text(s))
+        .collect(),
+    ];
+    let first = order.len() + 1;
+    let (mut maliyet, mut total_price) = (0., 0.);
+    let shares = price.setup_shares(consumables, item_count);
+    for (i, (consumable, setup)) in lines.clone().zip(shares).enumerate() {
+        let (line_maliyet, line_price) = price.calculate_line(consumable, setup);
+        maliyet += (line_maliyet as f64 * 100.).round() / 100.;
+        total_price += (line_price as f64 * 100.).round() / 100.;
+        order.push(vec![
+            Cell::Number(i as f64 + 1.),
+            Cell::Number(consumable.width as f64),
+            Cell::Number(consumable.height as f64),
+            text(consumable.get_plise_name().label()),
+            text(
+                format!(
+                    "{} {}",
+                    consumable.get_plise_color().label(),
+                    sanitize_line(&consumable.renk_kodu)
+                )
+                .trim_end(),
+            ),
+            money(line_maliyet),
+            money(line_price),
+        ]);
+    }
+    let last = order.len();
+    let total_row = order.len() + 1;
+    let kdv = price.kdv as f64;
+    order.push(vec![
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        text("Toplam"),
+        Cell::Formula(format!("SUM(F{}:F{})", first, last), maliyet),
+        Cell::Formula(format!("SUM(G{}:G{})", first, last), total_price),
+    ]);
+    order.push(vec![
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        text("KDV (%)"),
+        Cell::Number(kdv),
+    ]);
+    order.push(vec![
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        Cell::Empty,
+        text("KDV Dahil"),
+        Cell::Formula(
+            format!("G{}*(1+G{}/100)", total_row, total_row + 1),
+            total_price * (1. + kdv / 100.),
+        ),
+    ]);
+
+    // Consumables sheet: the per-pleat material quantities with column totals. It has
+    // the same three leading rows as the order sheet, so `first` and `last` apply here too.
+    let mut sarf = vec![
+        vec![text("Müşteri Adı:"), text(client)],
+        vec![],
+        [
+            "Sıra",
+            "Plise Tipi",
+            "Renk Tipi",
+            "Tül Tipi",
+            "Kasa (cm)",
+            "Kanat (cm)",
+            "Tül (cm²)",
+            "Şerit (cm)",
+            "Köşe Adet",
+            "Teker Adet",
+            "Klips Adet",
+            "Stop Adet",
+            "Dönüş Adet",
+            "Adaptör (cm)",
+        ]
+        .iter()
+        .map(|s| text(s))
+        .collect(),
+    ];
+    let mut sums = [0f64; 10];
+    for (i, consumable) in lines.enumerate() {
+        let row = consumable.row(i + 1);
+        let quantities = [
+            row.kasa_cm,
+            row.kanat_cm,
+            row.tul_cm_squared,
+            row.serit_cm,
+            row.kose_adet,
+            row.teker_adet,
+            row.klips_adet,
+            row.stop_adet,
+            row.donus_adet,
+            row.adaptor_cm,
+        ];
+        let mut cells = vec![
+            Cell::Number(row.idx as f64),
+            text(row.plise_name),
+            text(format!("{} {}", row.color, row.renk_kodu).trim_end()),
+            text(row.tul),
+        ];
+        for (sum, quantity) in sums.iter_mut().zip(quantities) {
+            *sum += quantity as f64;
+            cells.push(Cell::Number(quantity as f64));
+        }
+        sarf.push(cells);
+    }
+    let mut totals = vec![Cell::Empty, Cell::Empty, Cell::Empty, text("Toplam")];
+    for (i, sum) in sums.iter().enumerate() {
+        let col = column_name(i + 4);
+        totals.push(Cell::Formula(
+            format!("SUM({}{}:{}{})", col, first, col, last),
+            *sum,
+        ));
+    }
+    sarf.push(totals);
+
+    vec![
+        Sheet {
+            name: "Siparis",
+            rows: order,
+        },
+        Sheet {
+            name: "Sarf",
+            rows: sarf,
+        },
+    ]
+}
+
+pub fn create_xlsx(
+    price: &Price,
+    consumables: &[Consumable],
+    item_count: u32,
+    client: &str,
+) -> Result<Vec<u8>, AppError> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let money = Format::new().set_num_format("#,##0.00");
+
+    for sheet in build_sheets(price, consumables, item_count, client) {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet.name)?;
+        for (row, cells) in sheet.rows.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                let (row, col) = (row as u32, col as u16);
+                match cell {
+                    Cell::Empty => {}
+                    Cell::Text(s) if row == 2 => {
+                        worksheet.write_string_with_format(row, col, s, &bold)?;
+                    }
+                    Cell::Text(s) => {
+                        worksheet.write_string(row, col, s)?;
+                    }
+                    Cell::Number(n) => {
+                        worksheet.write_number(row, col, *n)?;
+                    }
+                    Cell::Money(n) => {
+                        worksheet.write_number_with_format(row, col, *n, &money)?;
+                    }
+                    Cell::Formula(f, value) => {
+                        let formula = Formula::new(format!("={}", f)).set_result(value.to_string());
+                        worksheet.write_formula_with_format(row, col, formula, &money)?;
+                    }
+                }
+            }
+        }
+        worksheet.autofit();
+    }
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// Converts an A1 formula to OpenFormula, e.g. `SUM(F4:F6)` to `of:=SUM([.F4:.F6])`.
+fn open_formula(formula: &str) -> String {
+    let mut out = String::from("of:=");
+    let chars: Vec<char> = formula.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_uppercase() {
+            i += 1;
+        }
+        let letters_end = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        if letters_end > start && i > letters_end {
+            let reference: String = chars[start..i].iter().collect();
+            if out.ends_with(':') {
+                out.pop();
+                out.pop();
+                out.push_str(&format!(":.{}]", reference));
+            } else {
+                out.push_str(&format!("[.{}]", reference));
+            }
+        } else if i > start {
+            out.extend(&chars[start..i]);
+        } else {
+            out.push(chars[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+fn ods_content(sheets: &[Sheet]) -> String {
+    let mut tables = String::new();
+    for sheet in sheets {
+        tables.push_str(&format!("<table:table table:name=\"{}\">", sheet.name));
+        for cells in &sheet.rows {
+            tables.push_str("<table:table-row>");
+            if cells.is_empty() {
+                tables.push_str("<table:table-cell/>");
+            }
+            for cell in cells {
+                match cell {
+                    Cell::Empty => tables.push_str("<table:table-cell/>"),
+                    Cell::Text(s) => tables.push_str(&format!(
+                        "<table:table-cell office:value-type=\"string\"><text:p>{}</text:p></table:table-cell>",
+                        escape_html(s)
+                    )),
+                    Cell::Number(n) => tables.push_str(&format!(
+                        "<table:table-cell office:value-type=\"float\" office:value=\"{}\"><text:p>{}</text:p></table:table-cell>",
+                        n, n
+                    )),
+                    Cell::Money(n) => tables.push_str(&format!(
+                        "<table:table-cell office:value-type=\"float\" office:value=\"{}\"><text:p>{:.2}</text:p></table:table-cell>",
+                        n, n
+                    )),
+                    Cell::Formula(f, value) => tables.push_str(&format!(
+                        "<table:table-cell table:formula=\"{}\" office:value-type=\"float\" office:value=\"{}\"><text:p>{:.2}</text:p></table:table-cell>",
+                        escape_html(&open_formula(f)),
+                        value,
+                        value
+                    )),
+                }
+            }
+            tables.push_str("</table:table-row>");
+        }
+        tables.push_str("</table:table>");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:of="urn:oasis:names:tc:opendocument:xmlns:of:1.2" office:version="1.2">
+<office:body><office:spreadsheet>{}</office:spreadsheet></office:body>
+</office:document-content>"#,
+        tables
+    )
+}
+
+const ODS_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>"#;
+
+pub fn create_ods(
+    price: &Price,
+    consumables: &[Consumable],
+    item_count: u32,
+    client: &str,
+) -> Result<Vec<u8>, AppError> {
+    let sheets = build_sheets(price, consumables, item_count, client);
+    Ok(write_ods(&sheets)?)
+}
+
+fn write_ods(sheets: &[Sheet]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+    // The mimetype entry must come first and be stored uncompressed.
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/vnd.oasis.opendocument.spreadsheet")?;
+    zip.start_file("META-INF/manifest.xml", FileOptions::default())?;
+    zip.write_all(ODS_MANIFEST.as_bytes())?;
+    zip.start_file("content.xml", FileOptions::default())?;
+    zip.write_all(ods_content(sheets).as_bytes())?;
+
+    Ok(zip.finish()?.into_inner())
+}
@@ -0,0 +1,785 @@
+use crate::consumable::Consumable;
+use crate::error::AppError;
+use crate::price::Price;
+use crate::settings;
+
+use chrono::prelude::Local;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Each entry upgrades the database by one version; `PRAGMA user_version` records
+/// how many have been applied. Never edit an entry once released, add a new one.
+const MIGRATIONS: &[&str] = &[
+    r#"
+CREATE TABLE price_lists (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE catalogue (
+    id INTEGER PRIMARY KEY,
+    price_list_id INTEGER NOT NULL REFERENCES price_lists(id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    name TEXT NOT NULL,
+    unit TEXT NOT NULL,
+    price REAL NOT NULL,
+    UNIQUE (price_list_id, code)
+);
+CREATE TABLE customers (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    phone TEXT NOT NULL DEFAULT '',
+    address TEXT NOT NULL DEFAULT '',
+    notes TEXT NOT NULL DEFAULT ''
+);
+CREATE TABLE quotes (
+    id INTEGER PRIMARY KEY,
+    number INTEGER NOT NULL,
+    customer_id INTEGER REFERENCES customers(id) ON DELETE SET NULL,
+    client_name TEXT NOT NULL,
+    price_list_id INTEGER REFERENCES price_lists(id),
+    created_at TEXT NOT NULL,
+    total REAL NOT NULL,
+    notes TEXT NOT NULL DEFAULT ''
+);
+CREATE TABLE order_lines (
+    id INTEGER PRIMARY KEY,
+    quote_id INTEGER NOT NULL REFERENCES quotes(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    width REAL NOT NULL,
+    height REAL NOT NULL,
+    plise_name TEXT NOT NULL,
+    color TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE VIRTUAL TABLE search USING fts5(
+    kind UNINDEXED,
+    ref_id UNINDEXED,
+    text,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+-- The tokenizer does not fold the Turkish dotless ı, so it is stored as i.
+CREATE TRIGGER customers_search_insert AFTER INSERT ON customers BEGIN
+    INSERT INTO search (kind, ref_id, text)
+    VALUES ('customer', new.id, replace(new.name || ' ' || new.phone || ' ' || new.address || ' ' || new.notes, 'ı', 'i'));
+END;
+CREATE TRIGGER customers_search_update AFTER UPDATE ON customers BEGIN
+    DELETE FROM search WHERE kind = 'customer' AND ref_id = old.id;
+    INSERT INTO search (kind, ref_id, text)
+    VALUES ('customer', new.id, replace(new.name || ' ' || new.phone || ' ' || new.address || ' ' || new.notes, 'ı', 'i'));
+END;
+CREATE TRIGGER customers_search_delete AFTER DELETE ON customers BEGIN
+    DELETE FROM search WHERE kind = 'customer' AND ref_id = old.id;
+END;
+CREATE TRIGGER quotes_search_insert AFTER INSERT ON quotes BEGIN
+    INSERT INTO search (kind, ref_id, text) VALUES ('quote', new.id, replace(new.client_name || ' ' || new.notes, 'ı', 'i'));
+END;
+CREATE TRIGGER quotes_search_update AFTER UPDATE ON quotes BEGIN
+    DELETE FROM search WHERE kind = 'quote' AND ref_id = old.id;
+    INSERT INTO search (kind, ref_id, text) VALUES ('quote', new.id, replace(new.client_name || ' ' || new.notes, 'ı', 'i'));
+END;
+CREATE TRIGGER quotes_search_delete AFTER DELETE ON quotes BEGIN
+    DELETE FROM search WHERE kind = 'quote' AND ref_id = old.id;
+END;
+"#,
+    r#"
+ALTER TABLE customers ADD COLUMN profile TEXT NOT NULL DEFAULT '';
+ALTER TABLE quotes ADD COLUMN profile TEXT NOT NULL DEFAULT '';
+"#,
+    r#"
+ALTER TABLE quotes ADD COLUMN accepted_at TEXT;
+CREATE TABLE schedule (
+    order_line_id INTEGER PRIMARY KEY REFERENCES order_lines(id) ON DELETE CASCADE,
+    day TEXT NOT NULL,
+    minutes REAL NOT NULL,
+    manual INTEGER NOT NULL DEFAULT 0
+);
+"#,
+    r#"
+CREATE TABLE stock (
+    item TEXT NOT NULL,
+    series TEXT NOT NULL DEFAULT '',
+    color TEXT NOT NULL DEFAULT '',
+    quantity REAL NOT NULL,
+    PRIMARY KEY (item, series, color)
+);
+"#,
+];
+
+#[derive(Clone, Default)]
+pub struct Customer {
+    pub id: Option<i64>,
+    pub name: String,
+    pub phone: String,
+    pub address: String,
+    pub notes: String,
+    /// Name of the price profile used for this customer, empty for the standard margins.
+    pub profile: String,
+}
+
+/// A quote to be saved, with its lines.
+pub struct Quote<'a> {
+    pub number: u32,
+    pub customer_id: Option<i64>,
+    pub client_name: &'a str,
+    pub notes: &'a str,
+    pub profile: &'a str,
+    pub total: f32,
+    pub lines: &'a [Consumable],
+}
+
+#[derive(Clone)]
+pub struct QuoteSummary {
+    pub id: i64,
+    pub number: u32,
+    pub client_name: String,
+    pub created_at: String,
+    pub total: f32,
+    pub notes: String,
+    pub profile: String,
+    pub accepted: bool,
+}
+
+/// A line of an accepted quote and the workshop day it is planned for.
+pub struct ProductionLine {
+    pub line_id: i64,
+    pub quote_id: i64,
+    pub number: u32,
+    pub client_name: String,
+    pub consumable: Consumable,
+    /// `%Y-%m-%d`, or `None` if not planned yet.
+    pub day: Option<String>,
+    pub minutes: f32,
+    /// Moved by hand, so planning again leaves it where it is.
+    pub manual: bool,
+}
+
+/// Material in stock, in the unit it is bought in. Series and colour are empty for
+/// materials that are the same for every pleat.
+#[derive(Clone)]
+pub struct StockLevel {
+    pub item: String,
+    pub series: String,
+    pub color: String,
+    pub quantity: f32,
+}
+
+/// Customers, quotes and the price list history, kept in `plise.sqlite` in the data folder.
+pub struct Store {
+    conn: Connection,
+}
+
+/// Turns what the user typed into an FTS5 query that matches words starting with
+/// each typed word, so quotes and operators in the input cannot cause a syntax error.
+fn fts_query(input: &str) -> String {
+    input
+        .replace('ı', "i")
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Store {
+    pub fn open() -> Result<Self, AppError> {
+        settings::create_data_dir()?;
+        let path = settings::settings_path("plise.sqlite");
+        Store::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, AppError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let store = Store { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    fn migrate(&self) -> Result<(), AppError> {
+        let version: usize = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.unchecked_transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Copies the prices from `prices.json` into the database the first time it is opened.
+    pub fn import_prices_once(&self, price: &Price) -> Result<(), AppError> {
+        let imported: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'prices_imported'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if imported.is_none() {
+            self.save_price_list("prices.json", price)?;
+            self.conn.execute(
+                "INSERT INTO meta (key, value) VALUES ('prices_imported', ?1)",
+                [Local::now().to_rfc3339()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Records a snapshot of the prices, with one catalogue row per component.
+    pub fn save_price_list(&self, name: &str, price: &Price) -> Result<i64, AppError> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO price_lists (name, created_at, data) VALUES (?1, ?2, ?3)",
+            params![
+                name,
+                Local::now().to_rfc3339(),
+                serde_json::to_string(price)?
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        for component in price.components() {
+            tx.execute(
+                "INSERT INTO catalogue (price_list_id, code, name, unit, price)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    id,
+                    component.key,
+                    component.label,
+                    component.unit,
+                    component.value
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn latest_price_list(&self) -> Result<Option<i64>, AppError> {
+        Ok(self
+            .conn
+            .query_row("SELECT MAX(id) FROM price_lists", [], |row| row.get(0))?)
+    }
+
+    pub fn save_customer(&self, customer: &Customer) -> Result<i64, AppError> {
+        match customer.id {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE customers SET name = ?2, phone = ?3, address = ?4, notes = ?5, profile = ?6
+                     WHERE id = ?1",
+                    params![
+                        id,
+                        customer.name,
+                        customer.phone,
+                        customer.address,
+                        customer.notes,
+                        customer.profile
+                    ],
+                )?;
+                Ok(id)
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO customers (name, phone, address, notes, profile)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        customer.name,
+                        customer.phone,
+                        customer.address,
+                        customer.notes,
+                        customer.profile
+                    ],
+                )?;
+                Ok(self.conn.last_insert_rowid())
+            }
+        }
+    }
+
+    /// Customers matching `query` in any field, or the most recent ones if it is empty.
+    pub fn search_customers(&self, query: &str) -> Result<Vec<Customer>, AppError> {
+        let query = fts_query(query);
+        let mut statement = if query.is_empty() {
+            self.conn.prepare(
+                "SELECT id, name, phone, address, notes, profile FROM customers
+                 WHERE ?1 = '' ORDER BY id DESC LIMIT 50",
+            )?
+        } else {
+            self.conn.prepare(
+                "SELECT id, name, phone, address, notes, profile FROM customers
+                 WHERE id IN (SELECT ref_id FROM search WHERE kind = 'customer' AND search MATCH ?1)
+                 ORDER BY id DESC LIMIT 50",
+            )?
+        };
+        let customers = statement
+            .query_map([query], |row| {
+                Ok(Customer {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    phone: row.get(2)?,
+                    address: row.get(3)?,
+                    notes: row.get(4)?,
+                    profile: row.get(5)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(customers)
+    }
+
+    pub fn save_quote(&self, quote: &Quote) -> Result<i64, AppError> {
+        let price_list_id = self.latest_price_list()?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO quotes
+                 (number, customer_id, client_name, price_list_id, created_at, total, notes, profile)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                quote.number,
+                quote.customer_id,
+                quote.client_name,
+                price_list_id,
+                Local::now().to_rfc3339(),
+                quote.total,
+                quote.notes,
+                quote.profile
+            ],
+        )?;
+        let quote_id = tx.last_insert_rowid();
+        for (position, line) in quote.lines.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO order_lines (quote_id, position, width, height, plise_name, color, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    quote_id,
+                    position + 1,
+                    line.width,
+                    line.height,
+                    line.get_plise_name().label(),
+                    line.get_plise_color().label(),
+                    serde_json::to_string(line)?
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(quote_id)
+    }
+
+    /// Quotes whose client name or notes match `query`, or the most recent ones if it is empty.
+    pub fn search_quotes(&self, query: &str) -> Result<Vec<QuoteSummary>, AppError> {
+        let query = fts_query(query);
+        let mut statement = if query.is_empty() {
+            self.conn.prepare(
+                "SELECT id, number, client_name, created_at, total, notes, profile, accepted_at IS NOT NULL
+                 FROM quotes
+                 WHERE ?1 = '' ORDER BY id DESC LIMIT 50",
+            )?
+        } else {
+            self.conn.prepare(
+                "SELECT id, number, client_name, created_at, total, notes, profile, accepted_at IS NOT NULL
+                 FROM quotes
+                 WHERE id IN (SELECT ref_id FROM search WHERE kind = 'quote' AND search MATCH ?1)
+                 ORDER BY id DESC LIMIT 50",
+            )?
+        };
+        let quotes = statement
+            .query_map([query], |row| {
+                Ok(QuoteSummary {
+                    id: row.get(0)?,
+                    number: row.get(1)?,
+                    client_name: row.get(2)?,
+                    created_at: row.get(3)?,
+                    total: row.get(4)?,
+                    notes: row.get(5)?,
+                    profile: row.get(6)?,
+                    accepted: row.get(7)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(quotes)
+    }
+
+    pub fn quote_lines(&self, quote_id: i64) -> Result<Vec<Consumable>, AppError> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT data FROM order_lines WHERE quote_id = ?1 ORDER BY position")?;
+        let lines = statement
+            .query_map([quote_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        lines
+            .iter()
+            .map(|data| Ok(serde_json::from_str(data)?))
+            .collect()
+    }
+
+    /// Marks a quote as accepted, so its lines go into production planning.
+    pub fn accept_quote(&self, quote_id: i64) -> Result<(), AppError> {
+        self.conn.execute(
+            "UPDATE quotes SET accepted_at = ?2 WHERE id = ?1 AND accepted_at IS NULL",
+            params![quote_id, Local::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Lines of accepted quotes, in the order the quotes were accepted.
+    pub fn production_lines(&self) -> Result<Vec<ProductionLine>, AppError> {
+        let mut statement = self.conn.prepare(
+            "SELECT l.id, q.id, q.number, q.client_name, l.data, s.day, s.minutes, s.manual
+             FROM order_lines l
+             JOIN quotes q ON q.id = l.quote_id
+             LEFT JOIN schedule s ON s.order_line_id = l.id
+             WHERE q.accepted_at IS NOT NULL
+             ORDER BY q.accepted_at, q.id, l.position",
+        )?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, u32>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<f32>>(6)?,
+                    row.get::<_, Option<bool>>(7)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(
+                |(line_id, quote_id, number, client_name, data, day, minutes, manual)| {
+                    Ok(ProductionLine {
+                        line_id,
+                        quote_id,
+                        number,
+                        client_name,
+                        consumable: serde_json::from_str(&data)?,
+                        day,
+                        minutes: minutes.unwrap_or(0.),
+                        manual: manual.unwrap_or(false),
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Stores the planned day and assembly minutes of each line.
+    pub fn save_schedule(&self, lines: &[ProductionLine]) -> Result<(), AppError> {
+        let tx = self.conn.unchecked_transaction()?;
+        for line in lines {
+            match &line.day {
+                Some(day) => tx.execute(
+                    "INSERT OR REPLACE INTO schedule (order_line_id, day, minutes, manual)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![line.line_id, day, line.minutes, line.manual],
+                )?,
+                None => tx.execute(
+                    "DELETE FROM schedule WHERE order_line_id = ?1",
+                    [line.line_id],
+                )?,
+            };
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn stock(&self) -> Result<Vec<StockLevel>, AppError> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT item, series, color, quantity FROM stock")?;
+        let levels = statement
+            .query_map([], |row| {
+                Ok(StockLevel {
+                    item: row.get(0)?,
+                    series: row.get(1)?,
+                    color: row.get(2)?,
+                    quantity: row.get(3)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(levels)
+    }
+
+    pub fn set_stock(&self, level: &StockLevel) -> Result<(), AppError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO stock (item, series, color, quantity) VALUES (?1, ?2, ?3, ?4)",
+            params![level.item, level.series, level.color, level.quantity],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plise::{ColorName, PliseName};
+
+    fn store() -> Store {
+        Store::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn user_version(store: &Store) -> usize {
+        store
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    fn count(store: &Store, table: &str) -> i64 {
+        store
+            .conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    fn customer(name: &str, notes: &str) -> Customer {
+        Customer {
+            name: name.to_string(),
+            notes: notes.to_string(),
+            ..Customer::default()
+        }
+    }
+
+    fn quote<'a>(client_name: &'a str, notes: &'a str, lines: &'a [Consumable]) -> Quote<'a> {
+        Quote {
+            number: 1,
+            customer_id: None,
+            client_name,
+            notes,
+            profile: "",
+            total: 100.,
+            lines,
+        }
+    }
+
+    fn customer_names(store: &Store, query: &str) -> Vec<String> {
+        let customers = store.search_customers(query).unwrap();
+        customers
+            .into_iter()
+            .map(|customer| customer.name)
+            .collect()
+    }
+
+    #[test]
+    fn migrates_a_new_database_to_the_latest_version() {
+        let store = store();
+        assert_eq!(user_version(&store), MIGRATIONS.len());
+
+        // Opening it again applies nothing twice.
+        store.migrate().unwrap();
+        assert_eq!(user_version(&store), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn migrates_an_older_database_keeping_its_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute("INSERT INTO customers (name) VALUES ('Eski Müşteri')", [])
+            .unwrap();
+
+        let store = Store::with_connection(conn).unwrap();
+        assert_eq!(user_version(&store), MIGRATIONS.len());
+        let customers = store.search_customers("").unwrap();
+        assert_eq!(customers.len(), 1);
+        assert_eq!(customers[0].name, "Eski Müşteri");
+        assert_eq!(customers[0].profile, "");
+        assert!(store.stock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_folds_turkish_letters() {
+        let store = store();
+        store.save_customer(&customer("Işık Yılmaz", "")).unwrap();
+        store
+            .save_customer(&customer("Ali Veli", "Çankaya şubesi"))
+            .unwrap();
+
+        assert_eq!(customer_names(&store, "isik"), ["Işık Yılmaz"]);
+        assert_eq!(customer_names(&store, "ışık"), ["Işık Yılmaz"]);
+        assert_eq!(customer_names(&store, "YIL"), ["Işık Yılmaz"]);
+        assert_eq!(customer_names(&store, "cankaya sube"), ["Ali Veli"]);
+        assert_eq!(customer_names(&store, "").len(), 2);
+    }
+
+    #[test]
+    fn search_treats_quotes_and_operators_as_text() {
+        let store = store();
+        store
+            .save_customer(&customer("Ali \"Usta\" NOT", "AND OR * ( )"))
+            .unwrap();
+        store.save_customer(&customer("Veli", "")).unwrap();
+
+        for query in [
+            "\"", "\"Usta", "Ali\" OR", "NOT", "AND", "*", "(", "a:b", "-ali", "^",
+        ] {
+            assert!(store.search_customers(query).is_ok(), "{}", query);
+            assert!(store.search_quotes(query).is_ok(), "{}", query);
+        }
+        assert_eq!(customer_names(&store, "\"usta\""), ["Ali \"Usta\" NOT"]);
+        assert_eq!(customer_names(&store, "NOT"), ["Ali \"Usta\" NOT"]);
+        assert_eq!(customer_names(&store, "Veli OR Ali").len(), 0);
+    }
+
+    #[test]
+    fn search_follows_customer_edits() {
+        let store = store();
+        let id = store.save_customer(&customer("Ayşe", "")).unwrap();
+        let edited = Customer {
+            id: Some(id),
+            ..customer("Fatma", "")
+        };
+        assert_eq!(store.save_customer(&edited).unwrap(), id);
+
+        assert!(customer_names(&store, "ayse").is_empty());
+        assert_eq!(customer_names(&store, "fatma"), ["Fatma"]);
+    }
+
+    #[test]
+    fn imports_the_prices_only_once() {
+        let store = store();
+        let price = Price::default();
+        store.import_prices_once(&price).unwrap();
+        store.import_prices_once(&price).unwrap();
+
+        assert_eq!(count(&store, "price_lists"), 1);
+        assert_eq!(count(&store, "catalogue"), price.components().len() as i64);
+    }
+
+    #[test]
+    fn saved_quotes_reload_their_lines() {
+        let store = store();
+        let price_list = store
+            .save_price_list("prices.json", &Price::default())
+            .unwrap();
+
+        let mut first = Consumable {
+            width: 120.,
+            height: 210.,
+            oda: "Salon".to_string(),
+            ..Consumable::default()
+        };
+        first.set_plise_name(PliseName::Genis);
+        let mut second = Consumable {
+            width: 60.5,
+            height: 90.,
+            renk_kodu: "RAL 7016".to_string(),
+            ..Consumable::default()
+        };
+        second.set_plise_color(ColorName::Ozel);
+        let lines = [first, second];
+        let id = store
+            .save_quote(&quote("Işık Yılmaz", "balkon", &lines))
+            .unwrap();
+
+        let reloaded = store.quote_lines(id).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!((reloaded[0].width, reloaded[0].height), (120., 210.));
+        assert_eq!(reloaded[0].get_plise_name(), PliseName::Genis);
+        assert_eq!(reloaded[0].oda, "Salon");
+        assert_eq!(reloaded[1].width, 60.5);
+        assert!(reloaded[1].get_plise_color() == ColorName::Ozel);
+        assert_eq!(reloaded[1].renk_kodu, "RAL 7016");
+
+        let quotes = store.search_quotes("balkon").unwrap();
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].id, id);
+        assert_eq!(quotes[0].client_name, "Işık Yılmaz");
+        assert!(!quotes[0].accepted);
+        let quote_price_list: i64 = store
+            .conn
+            .query_row(
+                "SELECT price_list_id FROM quotes WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(quote_price_list, price_list);
+    }
+
+    #[test]
+    fn schedules_lines_of_accepted_quotes() {
+        let store = store();
+        let lines = vec![Consumable::default(); 2];
+        let pending = store.save_quote(&quote("Bekleyen", "", &lines)).unwrap();
+        let accepted = store.save_quote(&quote("Onaylı", "", &lines)).unwrap();
+        store.accept_quote(accepted).unwrap();
+
+        let mut production = store.production_lines().unwrap();
+        assert_eq!(production.len(), 2);
+        assert!(production.iter().all(|line| line.quote_id == accepted));
+        assert!(production.iter().all(|line| line.day.is_none()));
+
+        production[0].day = Some("2026-10-20".to_string());
+        production[0].minutes = 45.;
+        production[0].manual = true;
+        production[1].day = Some("2026-10-21".to_string());
+        store.save_schedule(&production).unwrap();
+        let production = store.production_lines().unwrap();
+        assert_eq!(production[0].day.as_deref(), Some("2026-10-20"));
+        assert_eq!(production[0].minutes, 45.);
+        assert!(production[0].manual);
+        assert_eq!(production[1].day.as_deref(), Some("2026-10-21"));
+
+        // Taking a line off the plan removes its row.
+        let mut production = production;
+        production[1].day = None;
+        store.save_schedule(&production).unwrap();
+        assert_eq!(count(&store, "schedule"), 1);
+        assert!(store.production_lines().unwrap()[1].day.is_none());
+        assert_eq!(store.quote_lines(pending).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn stock_levels_are_replaced_per_item_series_and_colour() {
+        let store = store();
+        let level = |item: &str, series: &str, color: &str, quantity| StockLevel {
+            item: item.to_string(),
+            series: series.to_string(),
+            color: color.to_string(),
+            quantity,
+        };
+        store
+            .set_stock(&level("kasa", "Klasik", "Beyaz", 12.))
+            .unwrap();
+        store
+            .set_stock(&level("kasa", "Klasik", "Antrasit", 3.))
+            .unwrap();
+        store.set_stock(&level("ip", "", "", 100.)).unwrap();
+        store
+            .set_stock(&level("kasa", "Klasik", "Beyaz", 7.5))
+            .unwrap();
+
+        let mut stock: Vec<_> = store
+            .stock()
+            .unwrap()
+            .into_iter()
+            .map(|level| (level.item, level.series, level.color, level.quantity))
+            .collect();
+        stock.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            stock,
+            [
+                ("ip".to_string(), String::new(), String::new(), 100.),
+                (
+                    "kasa".to_string(),
+                    "Klasik".to_string(),
+                    "Antrasit".to_string(),
+                    3.
+                ),
+                (
+                    "kasa".to_string(),
+                    "Klasik".to_string(),
+                    "Beyaz".to_string(),
+                    7.5
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::consumable::Consumable;
+use crate::error::AppError;
+use crate::plise::{MountType, PliseName};
+use crate::settings::{self, Versioned};
+
+use serde::{Deserialize, Serialize};
+
+/// The rough opening as the installer measured it, before any deduction.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Survey {
+    /// Top, middle and bottom.
+    pub widths: [f32; 3],
+    /// Left, middle and right.
+    pub heights: [f32; 3],
+}
+
+fn smallest(values: &[f32; 3]) -> Option<f32> {
+    values
+        .iter()
+        .copied()
+        .filter(|value| *value > 0.)
+        .reduce(f32::min)
+}
+
+impl Survey {
+    /// The narrowest width and the lowest height, since the pleat has to fit everywhere.
+    /// Points left at zero were not measured.
+    pub fn opening(&self) -> Option<(f32, f32)> {
+        Some((smallest(&self.widths)?, smallest(&self.heights)?))
+    }
+
+    /// One line for documents, e.g. `Üst/Orta/Alt 120/119.5/-, Sol/Orta/Sağ 150/150/149`,
+    /// with `-` for points not measured.
+    pub fn summary(&self) -> String {
+        let join = |values: &[f32; 3]| {
+            values
+                .iter()
+                .map(|value| match value {
+                    value if *value > 0. => value.to_string(),
+                    _ => "-".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        format!(
+            "Üst/Orta/Alt {}, Sol/Orta/Sağ {}",
+            join(&self.widths),
+            join(&self.heights)
+        )
+    }
+}
+
+/// What is taken off the rough opening for a series and mount type. Negative values
+/// make the pleat larger than the opening, as for face-fixed pleats that overlap it.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Deduction {
+    pub plise_name: PliseName,
+    pub mount: MountType,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurveySettings {
+    pub deductions: Vec<Deduction>,
+}
+
+impl Default for SurveySettings {
+    fn default() -> Self {
+        let deductions = PliseName::ALL
+            .iter()
+            .flat_map(|plise_name| {
+                MountType::ALL.iter().map(|mount| {
+                    let (width, height) = default_deduction(*plise_name, *mount);
+                    Deduction {
+                        plise_name: *plise_name,
+                        mount: *mount,
+                        width,
+                        height,
+                    }
+                })
+            })
+            .collect();
+        SurveySettings { deductions }
+    }
+}
+
+fn default_deduction(plise_name: PliseName, mount: MountType) -> (f32, f32) {
+    match (mount, plise_name) {
+        (MountType::Pervaz | MountType::Adaptor, PliseName::Ince) => (0.3, 0.3),
+        (MountType::Pervaz | MountType::Adaptor, _) => (0.5, 0.5),
+        // The kasa overlaps the opening by its own width on every side.
+        (MountType::Yuzey, PliseName::Ince) => (-3., -3.),
+        (MountType::Yuzey, PliseName::Klasik) => (-4., -4.),
+        (MountType::Yuzey, PliseName::Genis) => (-5., -5.),
+    }
+}
+
+impl Versioned for SurveySettings {
+    const FILE_NAME: &'static str = "survey.json";
+    const VERSION: u32 = 1;
+}
+
+impl SurveySettings {
+    pub fn create_from_file() -> Result<Self, AppError> {
+        settings::load(Self::FILE_NAME).map(SurveySettings::complete)
+    }
+
+    /// Adds the built-in deductions of series and mount types missing from the file,
+    /// so they can be edited too.
+    fn complete(mut self) -> Self {
+        for default in SurveySettings::default().deductions {
+            if !self
+                .deductions
+                .iter()
+                .any(|d| d.plise_name == default.plise_name && d.mount == default.mount)
+            {
+                self.deductions.push(default);
+            }
+        }
+        self
+    }
+
+    pub fn to_file(&self) -> Result<(), AppError> {
+        settings::save(Self::FILE_NAME, self)
+    }
+
+    /// Width and height deductions for a series and mount type, falling back to the
+    /// built-in ones for combinations missing from the file.
+    pub fn deduction(&self, plise_name: PliseName, mount: MountType) -> (f32, f32) {
+        self.deductions
+            .iter()
+            .find(|d| d.plise_name == plise_name && d.mount == mount)
+            .map_or_else(
+                || default_deduction(plise_name, mount),
+                |d| (d.width, d.height),
+            )
+    }
+
+    /// The finished pleat size for a line's survey, series and mount type.
+    pub fn finished_size(&self, consumable: &Consumable, survey: &Survey) -> Option<(f32, f32)> {
+        let (width, height) = survey.opening()?;
+        let (width_deduction, height_deduction) =
+            self.deduction(consumable.get_plise_name(), consumable.montaj);
+        Some((width - width_deduction, height - height_deduction))
+    }
+
+    /// Stores the survey on the line and fills its width and height from it.
+    pub fn apply(&self, consumable: &mut Consumable, survey: Survey) {
+        if let Some((width, height)) = self.finished_size(consumable, &survey) {
+            consumable.width = width;
+            consumable.height = height;
+        }
+        consumable.olcu = Some(survey);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reapplying_after_a_mount_change_uses_the_new_deduction() {
+        let settings = SurveySettings::default();
+        let survey = Survey {
+            widths: [120., 119.5, 0.],
+            heights: [150., 150., 149.],
+        };
+        let mut consumable = Consumable::default();
+        settings.apply(&mut consumable, survey);
+        assert_eq!((consumable.width, consumable.height), (119., 148.5));
+
+        consumable.montaj = MountType::Yuzey;
+        let survey = consumable.olcu.unwrap();
+        settings.apply(&mut consumable, survey);
+        assert_eq!((consumable.width, consumable.height), (123.5, 153.));
+    }
+}
@@ -0,0 +1,154 @@
+use crate::consumable::Consumable;
+use crate::plise::PliseName;
+use crate::price::Price;
+
+/// Hypothetical price changes, in percent. Applied to a copy of the prices, never saved.
+#[derive(Default)]
+pub struct WhatIf {
+    /// Profile metre prices of every colour, and the adapter profile.
+    pub profil: f32,
+    pub tul: f32,
+    /// Şerit, köşe, teker, klips, stop and dönüş.
+    pub hardware: f32,
+    /// The labour percentage itself, so 10 turns 30% labour into 33%, and the hourly rate.
+    pub isci: f32,
+}
+
+impl WhatIf {
+    pub fn apply(&self, price: &Price) -> Price {
+        let scale = |value: f32, change: f32| value * (1. + change / 100.);
+        let mut price = price.clone();
+        price.color_price.beyaz = scale(price.color_price.beyaz, self.profil);
+        price.color_price.boya = scale(price.color_price.boya, self.profil);
+        price.color_price.ahsap = scale(price.color_price.ahsap, self.profil);
+        price.color_price.ozel = scale(price.color_price.ozel, self.profil);
+        price.adaptor_price = scale(price.adaptor_price, self.profil);
+        for tul in [
+            &mut price.tul.standart,
+            &mut price.tul.evcil,
+            &mut price.tul.polen,
+            &mut price.tul.karartma,
+        ] {
+            tul.price = scale(tul.price, self.tul);
+        }
+        for hardware in [
+            &mut price.serit_price,
+            &mut price.klasik_kose_price,
+            &mut price.genis_kose_price,
+            &mut price.ince_kose_price,
+            &mut price.teker_price,
+            &mut price.klips_price,
+            &mut price.stop_price,
+            &mut price.donus_price,
+        ] {
+            *hardware = scale(*hardware, self.hardware);
+        }
+        price.isci_maliyeti = scale(price.isci_maliyeti, self.isci);
+        price.labour.hourly_rate = scale(price.labour.hourly_rate, self.isci);
+        price
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Delta {
+    pub old: f32,
+    pub new: f32,
+}
+
+impl Delta {
+    fn add(&mut self, old: f32, new: f32) {
+        self.old += old;
+        self.new += new;
+    }
+
+    pub fn difference(&self) -> f32 {
+        self.new - self.old
+    }
+
+    pub fn percent(&self) -> f32 {
+        if self.old != 0. {
+            self.difference() / self.old * 100.
+        } else {
+            0.
+        }
+    }
+}
+
+pub struct SeriesDelta {
+    pub plise_name: PliseName,
+    pub maliyet: Delta,
+    pub price: Delta,
+}
+
+/// One order to reprice: its lines and the price profile it was quoted with.
+pub struct Order<'a> {
+    pub name: String,
+    pub lines: &'a [Consumable],
+    pub profile: &'a str,
+}
+
+/// Costs and prices (without KDV) of a set of orders before and after the changes.
+pub struct Comparison {
+    pub components: Vec<(&'static str, Delta)>,
+    pub series: Vec<SeriesDelta>,
+    pub orders: Vec<(String, Delta)>,
+    pub maliyet: Delta,
+    pub price: Delta,
+}
+
+impl Comparison {
+    pub fn new(price: &Price, what_if: &WhatIf, orders: &[Order]) -> Self {
+        let mut comparison = Comparison {
+            components: Vec::new(),
+            series: Vec::new(),
+            orders: Vec::new(),
+            maliyet: Delta::default(),
+            price: Delta::default(),
+        };
+        for order in orders {
+            let old_price = price.with_profile(order.profile);
+            let new_price = what_if.apply(&old_price);
+            let mut order_total = Delta::default();
+            let count = order.lines.len() as u32;
+            let old_shares = old_price.setup_shares(order.lines, count);
+            let new_shares = new_price.setup_shares(order.lines, count);
+            for ((line, old_setup), new_setup) in order.lines.iter().zip(old_shares).zip(new_shares)
+            {
+                let old = old_price.cost_breakdown(line, old_setup);
+                let new = new_price.cost_breakdown(line, new_setup);
+                for ((label, old), (_, new)) in old.into_iter().zip(new) {
+                    match comparison.components.iter_mut().find(|(l, _)| *l == label) {
+                        Some((_, delta)) => delta.add(old, new),
+                        None => comparison.components.push((label, Delta { old, new })),
+                    }
+                }
+
+                let (old_maliyet, old_line_price) = old_price.calculate_line(line, old_setup);
+                let (new_maliyet, new_line_price) = new_price.calculate_line(line, new_setup);
+                let plise_name = line.get_plise_name();
+                let series = match comparison
+                    .series
+                    .iter()
+                    .position(|s| s.plise_name == plise_name)
+                {
+                    Some(i) => &mut comparison.series[i],
+                    None => {
+                        comparison.series.push(SeriesDelta {
+                            plise_name,
+                            maliyet: Delta::default(),
+                            price: Delta::default(),
+                        });
+                        comparison.series.last_mut().unwrap()
+                    }
+                };
+                series.maliyet.add(old_maliyet, new_maliyet);
+                series.price.add(old_line_price, new_line_price);
+                comparison.maliyet.add(old_maliyet, new_maliyet);
+                comparison.price.add(old_line_price, new_line_price);
+                order_total.add(old_line_price, new_line_price);
+            }
+            comparison.orders.push((order.name.clone(), order_total));
+        }
+        comparison
+    }
+}
@@ -0,0 +1,60 @@
+use crate::consumable::{Consumable, ConsumableRow, CutPiece};
+use crate::error::AppError;
+use crate::price::Price;
+
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::Serialize;
+
+/// One pleat on a work order, with everything the workshop needs to build it.
+#[derive(Serialize)]
+pub struct WorkOrderLine {
+    pub row: ConsumableRow,
+    pub cuts: Vec<CutPiece>,
+    pub minutes: String,
+    /// The surveyed rough opening, for checking the size on site.
+    pub olcu: Option<String>,
+    /// Printed under the QR code and encoded in it, e.g. `IE-0012-03`.
+    pub code: String,
+    pub qr_svg: String,
+}
+
+pub fn qr_svg(data: &str) -> Result<String, AppError> {
+    let svg = QrCode::new(data)?
+        .render::<svg::Color>()
+        .min_dimensions(96, 96)
+        .build();
+    // Inlined into HTML, where the XML declaration is not allowed.
+    Ok(match svg.find("<svg") {
+        Some(start) => svg[start..].to_string(),
+        None => svg,
+    })
+}
+
+pub fn order_code(document_number: u32) -> String {
+    format!("IE-{:04}", document_number)
+}
+
+pub fn lines(
+    consumables: &[Consumable],
+    item_count: u32,
+    document_number: u32,
+    price: &Price,
+) -> Result<Vec<WorkOrderLine>, AppError> {
+    consumables
+        .iter()
+        .take(item_count as usize)
+        .enumerate()
+        .map(|(i, consumable)| {
+            let code = format!("{}-{:02}", order_code(document_number), i + 1);
+            Ok(WorkOrderLine {
+                row: consumable.row(i + 1),
+                cuts: consumable.cut_list(),
+                minutes: format!("{:.0}", price.assembly_minutes(consumable)),
+                olcu: consumable.olcu.map(|survey| survey.summary()),
+                qr_svg: qr_svg(&code)?,
+                code,
+            })
+        })
+        .collect()
+}